@@ -0,0 +1,55 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Encode a response type (journeys, boards, train status...) with postcard for compact on-disk caches.
+///
+/// The encoding is positional: bytes written by a different crate version may not decode.
+pub fn to_cache_bytes<T: Serialize>(value: &T) -> anyhow::Result<Vec<u8>> {
+    Ok(postcard::to_stdvec(value)?)
+}
+
+/// Decode a value previously encoded with [`to_cache_bytes()`]
+pub fn from_cache_bytes<T: DeserializeOwned>(bytes: &[u8]) -> anyhow::Result<T> {
+    Ok(postcard::from_bytes(bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+
+    use crate::{BoardKind, JourneyResults, StationRealtime, TrainRealtime};
+
+    use super::*;
+
+    fn round_trip<T: Serialize + DeserializeOwned + Debug>(fixture: &str) -> T {
+        let value: T = serde_json::from_str(fixture).unwrap();
+        let decoded: T = from_cache_bytes(&to_cache_bytes(&value).unwrap()).unwrap();
+        assert_eq!(format!("{value:?}"), format!("{decoded:?}"));
+        decoded
+    }
+
+    #[test]
+    fn response_types_round_trip() {
+        let journeys: JourneyResults = round_trip(include_str!(
+            "../../tests/fixtures/journey_results_multi_day.json"
+        ));
+        assert_eq!(journeys.journeys().count(), 3);
+
+        let results: JourneyResults =
+            round_trip(include_str!("../../tests/fixtures/journey_results.json"));
+        assert_eq!(results.sorted_by_price()[0].cheapest_price(), Some(39.9));
+
+        round_trip::<JourneyResults>(include_str!(
+            "../../tests/fixtures/journey_results_italobus.json"
+        ));
+
+        let board: StationRealtime = round_trip(include_str!(
+            "../../tests/fixtures/station_realtime_MC_.json"
+        ));
+        assert_eq!(board.arrival_board()[0].kind(), &BoardKind::Arrival);
+
+        let train: TrainRealtime = round_trip(include_str!(
+            "../../tests/fixtures/train_realtime_8158.json"
+        ));
+        assert_eq!(train.train_schedule().disruption().delay_amount(), &3);
+    }
+}
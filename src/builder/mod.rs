@@ -0,0 +1,206 @@
+#[cfg(feature = "record")]
+use std::path::PathBuf;
+
+use std::time::Duration;
+
+use chrono_tz::Tz;
+use reqwest::{
+    header::{HeaderMap, HeaderValue, ACCEPT_LANGUAGE},
+    redirect::Policy,
+    Client,
+};
+
+use std::sync::Arc;
+
+use crate::{endpoint::EndpointConfig, Clock, ItaloApi, StationListMarkers, SystemClock};
+
+static DEFAULT_MAX_REDIRECTS: usize = 5;
+static DEFAULT_MAX_CONCURRENCY: usize = 4;
+static DEFAULT_ACCEPT_LANGUAGE: &str = "it-IT";
+static DEFAULT_MAX_RESPONSE_BYTES: usize = 10 * 1024 * 1024;
+static DEFAULT_STATION_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Configure and build an [`ItaloApi`] instance.
+///
+/// Use [`ItaloApi::builder()`] to get one.
+pub struct ItaloApiBuilder {
+    redirect: Policy,
+    display_timezone: Tz,
+    deadline: Option<Duration>,
+    endpoints: EndpointConfig,
+    max_concurrency: usize,
+    accept_language: String,
+    station_cache_ttl: Duration,
+    pool_idle_timeout: Option<Duration>,
+    pool_max_idle_per_host: Option<usize>,
+    max_response_bytes: usize,
+    auto_login: bool,
+    clock: Arc<dyn Clock>,
+    station_list_markers: StationListMarkers,
+    #[cfg(feature = "record")]
+    record_to: Option<PathBuf>,
+}
+
+impl Default for ItaloApiBuilder {
+    fn default() -> Self {
+        Self {
+            redirect: Policy::limited(DEFAULT_MAX_REDIRECTS),
+            display_timezone: Tz::UTC,
+            deadline: None,
+            endpoints: EndpointConfig::default(),
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            accept_language: DEFAULT_ACCEPT_LANGUAGE.to_owned(),
+            station_cache_ttl: DEFAULT_STATION_CACHE_TTL,
+            pool_idle_timeout: None,
+            pool_max_idle_per_host: None,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            auto_login: true,
+            clock: Arc::new(SystemClock),
+            station_list_markers: StationListMarkers::default(),
+            #[cfg(feature = "record")]
+            record_to: None,
+        }
+    }
+}
+
+impl ItaloApiBuilder {
+    /// Set the redirect policy used by the http client. Default follows up to 5 redirects.
+    pub fn redirect(mut self, policy: Policy) -> Self {
+        self.redirect = policy;
+        self
+    }
+
+    /// Set the timezone returned by [`ItaloApi::display_timezone()`]. Default is UTC.
+    pub fn display_timezone(mut self, tz: Tz) -> Self {
+        self.display_timezone = tz;
+        self
+    }
+
+    /// Set a wall-clock budget for operations issuing several requests.
+    ///
+    /// Sub-requests still pending when it expires are aborted and reported as [`crate::ItaloError::DeadlineExceeded`].
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Set the `Accept-Language` header sent with every request. Default is `it-IT`.
+    ///
+    /// The realtime services may localize free text accordingly, which affects
+    /// [`crate::StationTrainRealtime::status()`] parsing.
+    pub fn accept_language(mut self, language: &str) -> Self {
+        self.accept_language = language.to_owned();
+        self
+    }
+
+    /// Set how long [`ItaloApi::cached_station_list()`] reuses a scraped station list. Default is 24 hours.
+    pub fn station_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.station_cache_ttl = ttl;
+        self
+    }
+
+    /// Set how many requests the batch methods run simultaneously. Default is 4.
+    pub fn max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// Set how long an idle pooled connection is kept open. Default is the reqwest one (90 seconds).
+    ///
+    /// Lower it when the first call after a long pause fails with a connection reset.
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the maximum number of idle pooled connections kept per host. Default is unlimited.
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// Set the largest response body read, bigger ones fail with [`crate::ItaloError::ResponseTooLarge`]. Default is 10 MiB.
+    ///
+    /// Bodies are streamed so a misbehaving server can not make the client buffer them whole.
+    pub fn max_response_bytes(mut self, max: usize) -> Self {
+        self.max_response_bytes = max;
+        self
+    }
+
+    /// Log in on the first call needing a session. Default is true.
+    ///
+    /// When disabled those calls fail with [`crate::ItaloError::NotAuthenticated`] until [`ItaloApi::login()`]
+    /// succeeds, keeping login failures apart from the search ones.
+    pub fn auto_login(mut self, enabled: bool) -> Self {
+        self.auto_login = enabled;
+        self
+    }
+
+    /// Set the clock read by the time-relative methods, e.g. [`ItaloApi::next_train()`]. Default is the system time.
+    ///
+    /// Inject a [`crate::FixedClock`] to test them deterministically.
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Set the strings locating the stations inside the page scraped by [`ItaloApi::station_list()`].
+    ///
+    /// Advanced escape hatch for when italo renames the page variables, the defaults match the current page
+    pub fn station_list_markers(mut self, markers: StationListMarkers) -> Self {
+        self.station_list_markers = markers;
+        self
+    }
+
+    #[cfg(test)]
+    pub(crate) fn endpoints(mut self, endpoints: EndpointConfig) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// Record every request and raw response in a timestamped file inside the `dir` directory.
+    ///
+    /// Useful to report issues or turn real responses into test fixtures.
+    #[cfg(feature = "record")]
+    pub fn record_to(mut self, dir: PathBuf) -> Self {
+        self.record_to = Some(dir);
+        self
+    }
+
+    /// Build the configured [`ItaloApi`]
+    pub fn build(self) -> anyhow::Result<ItaloApi> {
+        let mut client =
+            Client::builder()
+                .redirect(self.redirect)
+                .default_headers(HeaderMap::from_iter([(
+                    ACCEPT_LANGUAGE,
+                    HeaderValue::from_str(&self.accept_language)?,
+                )]));
+        if let Some(timeout) = self.pool_idle_timeout {
+            client = client.pool_idle_timeout(timeout);
+        }
+        if let Some(max) = self.pool_max_idle_per_host {
+            client = client.pool_max_idle_per_host(max);
+        }
+        let client = client.build()?;
+
+        Ok(ItaloApi {
+            signature: Default::default(),
+            client,
+            display_timezone: self.display_timezone,
+            deadline: self.deadline,
+            endpoints: self.endpoints,
+            reachable_cache: Default::default(),
+            direct_cache: Default::default(),
+            station_cache: None,
+            station_cache_ttl: self.station_cache_ttl,
+            max_concurrency: self.max_concurrency,
+            max_response_bytes: self.max_response_bytes,
+            auto_login: self.auto_login,
+            clock: self.clock,
+            station_list_markers: self.station_list_markers,
+            #[cfg(feature = "record")]
+            record_to: self.record_to,
+        })
+    }
+}
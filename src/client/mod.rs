@@ -0,0 +1,505 @@
+use std::{collections::HashMap, future::Future, time::Duration};
+
+use futures::Stream;
+
+use crate::{
+    ItaloApi, Journey, JourneyLookup, JourneyRequest, JourneyResults, ParseWarning, Station,
+    StationLookup, StationRealtime, StationTrainRealtime, TrainRealtime,
+};
+
+/// Query operations of [`ItaloApi`] as a trait.
+///
+/// Depend on it instead of the concrete client to substitute a fake in tests. Session and maintenance
+/// calls (login, warmup, health and schema checks, cache refreshes, recording) are not part of it.
+pub trait ItaloClient {
+    /// See [`ItaloApi::station_list()`]
+    fn station_list(&self) -> impl Future<Output = anyhow::Result<Vec<Station>>> + Send;
+
+    /// See [`ItaloApi::station_list_lenient()`]
+    fn station_list_lenient(
+        &self,
+    ) -> impl Future<Output = anyhow::Result<(Vec<Station>, Vec<ParseWarning>)>> + Send;
+
+    /// See [`ItaloApi::cached_station_list()`]
+    fn cached_station_list(&mut self) -> impl Future<Output = anyhow::Result<Vec<Station>>> + Send;
+
+    /// See [`ItaloApi::station_by_url_coding()`]
+    fn station_by_url_coding(
+        &mut self,
+        slug: &str,
+    ) -> impl Future<Output = anyhow::Result<Option<Station>>> + Send;
+
+    /// See [`ItaloApi::resolve_station_names()`]
+    fn resolve_station_names(
+        &mut self,
+        codes: &[&str],
+    ) -> impl Future<Output = anyhow::Result<HashMap<String, String>>> + Send;
+
+    /// See [`ItaloApi::find_station()`]
+    fn find_station(
+        &self,
+        name: &str,
+    ) -> impl Future<Output = anyhow::Result<StationLookup>> + Send;
+
+    /// See [`ItaloApi::disambiguate()`]
+    fn disambiguate(&self, name: &str)
+        -> impl Future<Output = anyhow::Result<Vec<Station>>> + Send;
+
+    /// See [`ItaloApi::nearest_station()`]
+    fn nearest_station(
+        &self,
+        lat: f64,
+        lon: f64,
+    ) -> impl Future<Output = anyhow::Result<Station>> + Send;
+
+    /// See [`ItaloApi::station_realtime()`]
+    fn station_realtime(
+        &self,
+        station: Station,
+    ) -> impl Future<Output = anyhow::Result<StationRealtime>> + Send;
+
+    /// See [`ItaloApi::station_realtime_lenient()`]
+    fn station_realtime_lenient(
+        &self,
+        station: Station,
+    ) -> impl Future<Output = anyhow::Result<(StationRealtime, Vec<ParseWarning>)>> + Send;
+
+    /// See [`ItaloApi::station_realtime_many()`]
+    fn station_realtime_many(
+        &self,
+        stations: &[Station],
+    ) -> impl Future<Output = Vec<(Station, anyhow::Result<StationRealtime>)>> + Send;
+
+    /// See [`ItaloApi::train_realtime()`]
+    fn train_realtime(
+        &self,
+        train_code: &str,
+    ) -> impl Future<Output = anyhow::Result<TrainRealtime>> + Send;
+
+    /// See [`ItaloApi::train_realtime_many()`]
+    fn train_realtime_many(
+        &self,
+        train_codes: &[&str],
+    ) -> impl Future<Output = Vec<(String, anyhow::Result<TrainRealtime>)>> + Send;
+
+    /// See [`ItaloApi::track_until_arrival()`]
+    fn track_until_arrival(
+        &self,
+        train_code: &str,
+        interval: Duration,
+    ) -> impl Stream<Item = anyhow::Result<TrainRealtime>> + Send + '_;
+
+    /// See [`ItaloApi::rfi_number()`]
+    fn rfi_number(
+        &self,
+        train: &StationTrainRealtime,
+    ) -> impl Future<Output = anyhow::Result<String>> + Send;
+
+    /// See [`ItaloApi::find_journeys()`]
+    fn find_journeys(
+        &self,
+        journey: &JourneyRequest,
+    ) -> impl Future<Output = anyhow::Result<JourneyResults>> + Send;
+
+    /// See [`ItaloApi::find_journeys_lenient()`]
+    fn find_journeys_lenient(
+        &self,
+        journey: &JourneyRequest,
+    ) -> impl Future<Output = anyhow::Result<(JourneyResults, Vec<ParseWarning>)>> + Send;
+
+    /// See [`ItaloApi::find_journeys_by_name()`]
+    fn find_journeys_by_name(
+        &self,
+        from: &str,
+        to: &str,
+        journey: JourneyRequest,
+    ) -> impl Future<Output = anyhow::Result<JourneyLookup>> + Send;
+
+    /// See [`ItaloApi::find_journeys_from_city()`]
+    fn find_journeys_from_city(
+        &mut self,
+        city: &str,
+        to: &Station,
+        journey: &JourneyRequest,
+    ) -> impl Future<Output = anyhow::Result<JourneyResults>> + Send;
+
+    /// See [`ItaloApi::next_train()`]
+    fn next_train(
+        &self,
+        from: &Station,
+        to: &Station,
+    ) -> impl Future<Output = anyhow::Result<Option<Journey>>> + Send;
+
+    /// See [`ItaloApi::refresh_journey()`]
+    fn refresh_journey(
+        &self,
+        journey: &Journey,
+        search: &JourneyRequest,
+    ) -> impl Future<Output = anyhow::Result<Option<Journey>>> + Send;
+
+    /// See [`ItaloApi::has_direct_service()`]
+    fn has_direct_service(
+        &mut self,
+        from: &Station,
+        to: &Station,
+    ) -> impl Future<Output = anyhow::Result<bool>> + Send;
+
+    /// See [`ItaloApi::reachable_stations()`]
+    fn reachable_stations(
+        &mut self,
+        from: &Station,
+    ) -> impl Future<Output = anyhow::Result<Vec<Station>>> + Send;
+}
+
+impl ItaloClient for ItaloApi {
+    fn station_list(&self) -> impl Future<Output = anyhow::Result<Vec<Station>>> + Send {
+        ItaloApi::station_list(self)
+    }
+
+    fn station_list_lenient(
+        &self,
+    ) -> impl Future<Output = anyhow::Result<(Vec<Station>, Vec<ParseWarning>)>> + Send {
+        ItaloApi::station_list_lenient(self)
+    }
+
+    fn cached_station_list(&mut self) -> impl Future<Output = anyhow::Result<Vec<Station>>> + Send {
+        ItaloApi::cached_station_list(self)
+    }
+
+    fn station_by_url_coding(
+        &mut self,
+        slug: &str,
+    ) -> impl Future<Output = anyhow::Result<Option<Station>>> + Send {
+        ItaloApi::station_by_url_coding(self, slug)
+    }
+
+    fn resolve_station_names(
+        &mut self,
+        codes: &[&str],
+    ) -> impl Future<Output = anyhow::Result<HashMap<String, String>>> + Send {
+        ItaloApi::resolve_station_names(self, codes)
+    }
+
+    fn find_station(
+        &self,
+        name: &str,
+    ) -> impl Future<Output = anyhow::Result<StationLookup>> + Send {
+        ItaloApi::find_station(self, name)
+    }
+
+    fn disambiguate(
+        &self,
+        name: &str,
+    ) -> impl Future<Output = anyhow::Result<Vec<Station>>> + Send {
+        ItaloApi::disambiguate(self, name)
+    }
+
+    fn nearest_station(
+        &self,
+        lat: f64,
+        lon: f64,
+    ) -> impl Future<Output = anyhow::Result<Station>> + Send {
+        ItaloApi::nearest_station(self, lat, lon)
+    }
+
+    fn station_realtime(
+        &self,
+        station: Station,
+    ) -> impl Future<Output = anyhow::Result<StationRealtime>> + Send {
+        ItaloApi::station_realtime(self, station)
+    }
+
+    fn station_realtime_lenient(
+        &self,
+        station: Station,
+    ) -> impl Future<Output = anyhow::Result<(StationRealtime, Vec<ParseWarning>)>> + Send {
+        ItaloApi::station_realtime_lenient(self, station)
+    }
+
+    fn station_realtime_many(
+        &self,
+        stations: &[Station],
+    ) -> impl Future<Output = Vec<(Station, anyhow::Result<StationRealtime>)>> + Send {
+        ItaloApi::station_realtime_many(self, stations)
+    }
+
+    fn train_realtime(
+        &self,
+        train_code: &str,
+    ) -> impl Future<Output = anyhow::Result<TrainRealtime>> + Send {
+        ItaloApi::train_realtime(self, train_code)
+    }
+
+    fn train_realtime_many(
+        &self,
+        train_codes: &[&str],
+    ) -> impl Future<Output = Vec<(String, anyhow::Result<TrainRealtime>)>> + Send {
+        ItaloApi::train_realtime_many(self, train_codes)
+    }
+
+    fn track_until_arrival(
+        &self,
+        train_code: &str,
+        interval: Duration,
+    ) -> impl Stream<Item = anyhow::Result<TrainRealtime>> + Send + '_ {
+        ItaloApi::track_until_arrival(self, train_code, interval)
+    }
+
+    fn rfi_number(
+        &self,
+        train: &StationTrainRealtime,
+    ) -> impl Future<Output = anyhow::Result<String>> + Send {
+        ItaloApi::rfi_number(self, train)
+    }
+
+    fn find_journeys(
+        &self,
+        journey: &JourneyRequest,
+    ) -> impl Future<Output = anyhow::Result<JourneyResults>> + Send {
+        ItaloApi::find_journeys(self, journey)
+    }
+
+    fn find_journeys_lenient(
+        &self,
+        journey: &JourneyRequest,
+    ) -> impl Future<Output = anyhow::Result<(JourneyResults, Vec<ParseWarning>)>> + Send {
+        ItaloApi::find_journeys_lenient(self, journey)
+    }
+
+    fn find_journeys_by_name(
+        &self,
+        from: &str,
+        to: &str,
+        journey: JourneyRequest,
+    ) -> impl Future<Output = anyhow::Result<JourneyLookup>> + Send {
+        ItaloApi::find_journeys_by_name(self, from, to, journey)
+    }
+
+    fn find_journeys_from_city(
+        &mut self,
+        city: &str,
+        to: &Station,
+        journey: &JourneyRequest,
+    ) -> impl Future<Output = anyhow::Result<JourneyResults>> + Send {
+        ItaloApi::find_journeys_from_city(self, city, to, journey)
+    }
+
+    fn next_train(
+        &self,
+        from: &Station,
+        to: &Station,
+    ) -> impl Future<Output = anyhow::Result<Option<Journey>>> + Send {
+        ItaloApi::next_train(self, from, to)
+    }
+
+    fn refresh_journey(
+        &self,
+        journey: &Journey,
+        search: &JourneyRequest,
+    ) -> impl Future<Output = anyhow::Result<Option<Journey>>> + Send {
+        ItaloApi::refresh_journey(self, journey, search)
+    }
+
+    fn has_direct_service(
+        &mut self,
+        from: &Station,
+        to: &Station,
+    ) -> impl Future<Output = anyhow::Result<bool>> + Send {
+        ItaloApi::has_direct_service(self, from, to)
+    }
+
+    fn reachable_stations(
+        &mut self,
+        from: &Station,
+    ) -> impl Future<Output = anyhow::Result<Vec<Station>>> + Send {
+        ItaloApi::reachable_stations(self, from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use futures::stream;
+
+    use super::*;
+
+    struct FakeClient;
+
+    fn milano() -> Station {
+        Station::new(
+            "MC_".into(),
+            "milano-centrale".into(),
+            "Milano Centrale".into(),
+        )
+    }
+
+    fn board() -> anyhow::Result<StationRealtime> {
+        Ok(serde_json::from_str(include_str!(
+            "../../tests/fixtures/station_realtime_MC_.json"
+        ))?)
+    }
+
+    fn train() -> anyhow::Result<TrainRealtime> {
+        Ok(serde_json::from_str(include_str!(
+            "../../tests/fixtures/train_realtime_8158.json"
+        ))?)
+    }
+
+    impl ItaloClient for FakeClient {
+        async fn station_list(&self) -> anyhow::Result<Vec<Station>> {
+            Ok(vec![milano()])
+        }
+
+        async fn station_list_lenient(&self) -> anyhow::Result<(Vec<Station>, Vec<ParseWarning>)> {
+            Ok((vec![milano()], Vec::new()))
+        }
+
+        async fn cached_station_list(&mut self) -> anyhow::Result<Vec<Station>> {
+            self.station_list().await
+        }
+
+        async fn station_by_url_coding(&mut self, _: &str) -> anyhow::Result<Option<Station>> {
+            Ok(Some(milano()))
+        }
+
+        async fn resolve_station_names(
+            &mut self,
+            _: &[&str],
+        ) -> anyhow::Result<HashMap<String, String>> {
+            Ok(HashMap::from([("MC_".into(), "Milano Centrale".into())]))
+        }
+
+        async fn find_station(&self, name: &str) -> anyhow::Result<StationLookup> {
+            Ok(StationLookup::search(vec![milano()], name))
+        }
+
+        async fn disambiguate(&self, _: &str) -> anyhow::Result<Vec<Station>> {
+            Ok(vec![milano()])
+        }
+
+        async fn nearest_station(&self, _: f64, _: f64) -> anyhow::Result<Station> {
+            Ok(milano())
+        }
+
+        async fn station_realtime(&self, _: Station) -> anyhow::Result<StationRealtime> {
+            board()
+        }
+
+        async fn station_realtime_lenient(
+            &self,
+            _: Station,
+        ) -> anyhow::Result<(StationRealtime, Vec<ParseWarning>)> {
+            Ok((board()?, Vec::new()))
+        }
+
+        async fn station_realtime_many(
+            &self,
+            stations: &[Station],
+        ) -> Vec<(Station, anyhow::Result<StationRealtime>)> {
+            stations
+                .iter()
+                .map(|station| (station.clone(), board()))
+                .collect()
+        }
+
+        async fn train_realtime(&self, _: &str) -> anyhow::Result<TrainRealtime> {
+            train()
+        }
+
+        async fn train_realtime_many(
+            &self,
+            train_codes: &[&str],
+        ) -> Vec<(String, anyhow::Result<TrainRealtime>)> {
+            train_codes
+                .iter()
+                .map(|code| (code.to_string(), train()))
+                .collect()
+        }
+
+        fn track_until_arrival(
+            &self,
+            _: &str,
+            _: Duration,
+        ) -> impl Stream<Item = anyhow::Result<TrainRealtime>> + Send + '_ {
+            stream::once(async { train() })
+        }
+
+        async fn rfi_number(&self, _: &StationTrainRealtime) -> anyhow::Result<String> {
+            Ok("9591".into())
+        }
+
+        async fn find_journeys(&self, _: &JourneyRequest) -> anyhow::Result<JourneyResults> {
+            Err(anyhow!("No journeys"))
+        }
+
+        async fn find_journeys_lenient(
+            &self,
+            _: &JourneyRequest,
+        ) -> anyhow::Result<(JourneyResults, Vec<ParseWarning>)> {
+            Err(anyhow!("No journeys"))
+        }
+
+        async fn find_journeys_by_name(
+            &self,
+            _: &str,
+            _: &str,
+            _: JourneyRequest,
+        ) -> anyhow::Result<JourneyLookup> {
+            Err(anyhow!("No journeys"))
+        }
+
+        async fn find_journeys_from_city(
+            &mut self,
+            _: &str,
+            _: &Station,
+            _: &JourneyRequest,
+        ) -> anyhow::Result<JourneyResults> {
+            Err(anyhow!("No journeys"))
+        }
+
+        async fn next_train(&self, _: &Station, _: &Station) -> anyhow::Result<Option<Journey>> {
+            Ok(None)
+        }
+
+        async fn refresh_journey(
+            &self,
+            _: &Journey,
+            _: &JourneyRequest,
+        ) -> anyhow::Result<Option<Journey>> {
+            Ok(None)
+        }
+
+        async fn has_direct_service(&mut self, _: &Station, _: &Station) -> anyhow::Result<bool> {
+            Ok(false)
+        }
+
+        async fn reachable_stations(&mut self, _: &Station) -> anyhow::Result<Vec<Station>> {
+            Ok(Vec::new())
+        }
+    }
+
+    async fn first_departure(client: &impl ItaloClient) -> anyhow::Result<String> {
+        let station = client.station_list().await?.remove(0);
+        let board = client.station_realtime(station).await?;
+        Ok(board.departure_board()[0].number().clone())
+    }
+
+    async fn direct_trains(client: &mut impl ItaloClient) -> anyhow::Result<Option<bool>> {
+        let from = client.nearest_station(45.48, 9.2).await?;
+        let to = client.disambiguate("Milano").await?.remove(0);
+        if !client.has_direct_service(&from, &to).await? {
+            return Ok(None);
+        }
+        Ok(Some(client.next_train(&from, &to).await?.is_some()))
+    }
+
+    #[tokio::test]
+    async fn fake_client() {
+        assert_eq!(first_departure(&FakeClient).await.unwrap(), "9941");
+        assert_eq!(direct_trains(&mut FakeClient).await.unwrap(), None);
+        assert!(FakeClient
+            .find_journeys(&JourneyRequest::default())
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,29 @@
+use chrono::{DateTime, Utc};
+
+/// Source of the current time for the time-relative helpers of [`crate::ItaloApi`].
+///
+/// Set it with [`crate::ItaloApiBuilder::clock()`], e.g. to a [`FixedClock`] in tests
+pub trait Clock: Send + Sync {
+    /// Current time
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// System time, the default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Clock frozen at a given time
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
@@ -0,0 +1,31 @@
+use tokio::sync::watch;
+
+/// Pause and resume a polling stream without recreating it, see [`crate::ItaloApi::track_until_arrival_controlled()`].
+///
+/// While paused no request is sent, a poll already in flight completes. Dropping the handle resumes the stream
+#[derive(Debug)]
+pub struct StreamControl {
+    paused: watch::Sender<bool>,
+}
+
+impl StreamControl {
+    pub(crate) fn new() -> (Self, watch::Receiver<bool>) {
+        let (paused, receiver) = watch::channel(false);
+        (Self { paused }, receiver)
+    }
+
+    /// Stop polling, e.g. when the app goes to the background
+    pub fn pause(&self) {
+        self.paused.send_replace(true);
+    }
+
+    /// Poll again, the next request is sent right away if its interval already elapsed
+    pub fn resume(&self) {
+        self.paused.send_replace(false);
+    }
+
+    /// The stream is paused
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+}
@@ -0,0 +1,51 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{de::Error, Deserialize, Deserializer};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumberOrString<T> {
+    Number(T),
+    String(String),
+}
+
+/// Deserialize an amount sent either as a JSON number or as an italian formatted string like `"1.049,90"`
+///
+/// Binary formats, which are not self describing, read the plain number
+pub fn italian_decimal<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    if !deserializer.is_human_readable() {
+        return f64::deserialize(deserializer);
+    }
+    match NumberOrString::<f64>::deserialize(deserializer)? {
+        NumberOrString::Number(val) => Ok(val),
+        NumberOrString::String(val) => {
+            let val = val.trim();
+            let normalized = match val.contains(',') {
+                true => val.replace('.', "").replace(',', "."),
+                false => val.to_owned(),
+            };
+            normalized.parse().map_err(Error::custom)
+        }
+    }
+}
+
+/// Deserialize a number that italotreno may encode either as a JSON number or as a string
+///
+/// Binary formats, which are not self describing, read the plain number
+pub fn number_or_string<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + FromStr,
+    T::Err: Display,
+{
+    if !deserializer.is_human_readable() {
+        return T::deserialize(deserializer);
+    }
+    match NumberOrString::<T>::deserialize(deserializer)? {
+        NumberOrString::Number(val) => Ok(val),
+        NumberOrString::String(val) => val.trim().parse().map_err(Error::custom),
+    }
+}
@@ -0,0 +1,102 @@
+static BIG_HOST: &str = "https://big.ntvspa.it";
+static REALTIME_HOST: &str = "https://italoinviaggio.italotreno.it";
+
+static LOGIN_PATH: &str = "/BIG/v7/Rest/SessionManager.svc/Login";
+static SEARCH_SOLUTIONS_PATH: &str = "/BIG/v7/Rest/BookingManager.svc/GetAvailableTrains";
+static BIG_REST_ROOT_PATH: &str = "/BIG/v7/Rest/";
+static STATION_LIST_PATH: &str = "/it/stazione";
+static STATION_REALTIME_PATH: &str = "/api/RicercaStazioneService?&CodiceStazione=";
+static TRAIN_REALTIME_PATH: &str = "/api/RicercaTrenoService?&TrainNumber=";
+
+/// Hosts contacted by [`crate::ItaloApi`], see [`crate::ItaloApi::endpoints()`].
+///
+/// Login, journey search and the other BIG REST calls go to [`Self::big_host()`], the station list
+/// and the station and train realtime calls to [`Self::realtime_host()`]
+#[derive(Debug, Clone)]
+pub struct EndpointConfig {
+    big_host: String,
+    realtime_host: String,
+}
+
+impl Default for EndpointConfig {
+    fn default() -> Self {
+        Self {
+            big_host: BIG_HOST.to_owned(),
+            realtime_host: REALTIME_HOST.to_owned(),
+        }
+    }
+}
+
+impl EndpointConfig {
+    #[cfg(test)]
+    pub(crate) fn new(big_host: &str, realtime_host: &str) -> Self {
+        Self {
+            big_host: big_host.to_owned(),
+            realtime_host: realtime_host.to_owned(),
+        }
+    }
+
+    /// Login and journey search host
+    pub fn big_host(&self) -> &str {
+        &self.big_host
+    }
+
+    /// Station and train realtime host
+    pub fn realtime_host(&self) -> &str {
+        &self.realtime_host
+    }
+
+    /// Every host the client may contact, e.g. to allow-list outbound traffic
+    pub fn hosts(&self) -> [&str; 2] {
+        [&self.big_host, &self.realtime_host]
+    }
+
+    pub(crate) fn login(&self) -> String {
+        self.big_host.clone() + LOGIN_PATH
+    }
+
+    pub(crate) fn search_solutions(&self) -> String {
+        self.big_host.clone() + SEARCH_SOLUTIONS_PATH
+    }
+
+    pub(crate) fn big_rest(&self, endpoint: &str) -> String {
+        self.big_host.clone() + BIG_REST_ROOT_PATH + endpoint.trim_start_matches('/')
+    }
+
+    pub(crate) fn station_list(&self) -> String {
+        self.realtime_host.clone() + STATION_LIST_PATH
+    }
+
+    pub(crate) fn station_realtime(&self, station_code: &str) -> String {
+        self.realtime_host.clone() + STATION_REALTIME_PATH + station_code
+    }
+
+    pub(crate) fn train_realtime(&self, train_code: &str) -> String {
+        self.realtime_host.clone() + TRAIN_REALTIME_PATH + train_code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls_stay_on_hosts() {
+        let config = EndpointConfig::default();
+        assert_eq!(config.hosts(), [BIG_HOST, REALTIME_HOST]);
+
+        for url in [
+            config.login(),
+            config.search_solutions(),
+            config.big_rest("BookingManager.svc/GetStations"),
+            config.station_list(),
+            config.station_realtime("MC_"),
+            config.train_realtime("8158"),
+        ] {
+            assert!(
+                config.hosts().iter().any(|host| url.starts_with(host)),
+                "{url}"
+            );
+        }
+    }
+}
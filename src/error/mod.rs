@@ -0,0 +1,110 @@
+use std::{fmt::Display, time::Duration};
+
+use getset::Getters;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// Errors raised by the crate on top of the transport and parsing ones.
+///
+/// They are returned wrapped in [`anyhow::Error`], use [`anyhow::Error::downcast_ref`] to match them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ItaloError {
+    /// The realtime service does not know the requested train
+    TrainNotFound(String),
+
+    /// Login refused, carries the server message
+    Auth(String),
+
+    /// The time budget configured with [`crate::ItaloApiBuilder::deadline()`] ran out
+    DeadlineExceeded,
+
+    /// The response body exceeded [`crate::ItaloApiBuilder::max_response_bytes()`], carries the limit
+    ResponseTooLarge(usize),
+
+    /// [`crate::ItaloApiBuilder::auto_login()`] is disabled and [`crate::ItaloApi::login()`] was not called
+    NotAuthenticated,
+
+    /// The server answered with a 429 or 5xx status
+    ServerError {
+        /// Http status code
+        status: u16,
+        /// Wait suggested by the `Retry-After` header, if any
+        retry_after: Option<Duration>,
+        /// Start of the response body
+        body_snippet: String,
+    },
+}
+
+impl Display for ItaloError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ItaloError::TrainNotFound(train) => write!(f, "Train {train} not found"),
+            ItaloError::DeadlineExceeded => write!(f, "Deadline exceeded"),
+            ItaloError::Auth(message) => write!(f, "Authentication failed: {message}"),
+            ItaloError::ResponseTooLarge(limit) => {
+                write!(f, "Response body larger than {limit} bytes")
+            }
+            ItaloError::NotAuthenticated => {
+                write!(f, "Not authenticated, call ItaloApi::login() first")
+            }
+            ItaloError::ServerError {
+                status,
+                retry_after,
+                body_snippet,
+            } => {
+                write!(f, "Server error {status}")?;
+                if let Some(retry_after) = retry_after {
+                    write!(f, ", retry after {}s", retry_after.as_secs())?;
+                }
+                write!(f, ": {body_snippet}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ItaloError {}
+
+/// An entry skipped by a lenient parser because it could not be decoded
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+#[get = "pub"]
+pub struct ParseWarning {
+    /// Name of the list holding the entry, as sent by the server
+    list: String,
+    /// Position of the entry inside the list
+    index: usize,
+    /// Why the entry was skipped
+    message: String,
+}
+
+impl ParseWarning {
+    pub(crate) fn new(list: &str, index: usize, message: String) -> Self {
+        Self {
+            list: list.to_owned(),
+            index,
+            message,
+        }
+    }
+}
+
+impl Display for ParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}[{}] skipped: {}", self.list, self.index, self.message)
+    }
+}
+
+/// Keep the entries of `list` decoding as `T`, the other ones are dropped and reported in `warnings`
+pub(crate) fn retain_decodable<T: DeserializeOwned>(
+    entries: &mut Vec<Value>,
+    list: &str,
+    warnings: &mut Vec<ParseWarning>,
+) {
+    let mut index = 0;
+    entries.retain(|entry| {
+        let parsed = T::deserialize(entry);
+        if let Err(err) = &parsed {
+            warnings.push(ParseWarning::new(list, index, err.to_string()));
+        }
+        index += 1;
+        parsed.is_ok()
+    });
+}
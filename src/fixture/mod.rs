@@ -0,0 +1,68 @@
+//! Fixture helpers for the test suite.
+//!
+//! Fixtures are read from `tests/fixtures/` and replayed through a [`MockServer`].
+//! Run the tests with `ITALO_CAPTURE=1` to replay live responses instead: they are saved under
+//! `tests/fixtures/captured/`, the committed fixtures are never overwritten and are refreshed by hand.
+
+use std::path::PathBuf;
+
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+use crate::{endpoint::EndpointConfig, ItaloApi};
+
+static CAPTURE_ENV: &str = "ITALO_CAPTURE";
+
+static CAPTURE_DIR: &str = "captured";
+
+fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+/// Tests replay live responses, value assertions on the fixtures do not hold
+pub fn capturing() -> bool {
+    std::env::var(CAPTURE_ENV).is_ok_and(|val| val == "1")
+}
+
+/// Read fixture `name`, or capture it from `live_url` into the capture directory when `ITALO_CAPTURE=1`
+pub async fn load(name: &str, live_url: &str) -> String {
+    let mut path = fixture_path(name);
+    if capturing() {
+        path = fixture_path(CAPTURE_DIR).join(name);
+        let body = reqwest::get(live_url)
+            .await
+            .and_then(|res| res.error_for_status())
+            .expect("Capture request failed")
+            .text()
+            .await
+            .expect("Capture body unreadable");
+        std::fs::create_dir_all(fixture_path(CAPTURE_DIR))
+            .expect("Failed to create capture directory");
+        std::fs::write(&path, body).expect("Failed to write fixture");
+    }
+
+    std::fs::read_to_string(&path).unwrap_or_else(|_| panic!("Missing fixture {}", path.display()))
+}
+
+/// Serve fixture `name` on GET requests to `route`, see [`load`]
+pub async fn serve(server: &MockServer, name: &str, live_url: &str, route: &str) {
+    Mock::given(method("GET"))
+        .and(path(route))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_raw(load(name, live_url).await, "application/json"),
+        )
+        .mount(server)
+        .await;
+}
+
+/// Interface pointing every endpoint to `server`
+pub fn api(server: &MockServer) -> ItaloApi {
+    ItaloApi::builder()
+        .endpoints(EndpointConfig::new(&server.uri(), &server.uri()))
+        .build()
+        .unwrap()
+}
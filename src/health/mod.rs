@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+use getset::Getters;
+
+/// Reachability of the italotreno backends, see [`crate::ItaloApi::health_check()`]
+#[derive(Debug, Clone, Getters)]
+#[get = "pub"]
+pub struct HealthStatus {
+    /// Login and journey search host (big.ntvspa.it)
+    login: EndpointHealth,
+
+    /// Station and train realtime host (italoinviaggio.italotreno.it)
+    realtime: EndpointHealth,
+}
+
+impl HealthStatus {
+    pub(crate) fn new(login: EndpointHealth, realtime: EndpointHealth) -> Self {
+        Self { login, realtime }
+    }
+
+    /// Both hosts answered
+    pub fn is_healthy(&self) -> bool {
+        self.login.reachable && self.realtime.reachable
+    }
+}
+
+/// Reachability of a single host
+#[derive(Debug, Clone, Getters)]
+#[get = "pub"]
+pub struct EndpointHealth {
+    /// Host has answered, whatever the http status
+    reachable: bool,
+
+    /// Time taken to answer, `None` when unreachable
+    latency: Option<Duration>,
+}
+
+impl EndpointHealth {
+    pub(crate) fn new(latency: Option<Duration>) -> Self {
+        Self {
+            reachable: latency.is_some(),
+            latency,
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Europe::Rome;
+use getset::Getters;
+use serde::{Deserialize, Serialize};
+
+use crate::TrainRealtime;
+
+/// Delay observed on a train by a realtime poll, see [`crate::ItaloApi::record_train()`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Getters)]
+#[get = "pub"]
+pub struct DelayRecord {
+    /// Italo ID
+    train_number: String,
+
+    /// Italian date of the trip
+    date: NaiveDate,
+
+    /// Poll time
+    recorded_at: DateTime<Utc>,
+
+    /// Delay (in minutes)
+    delay_minutes: i32,
+}
+
+impl DelayRecord {
+    /// Snapshot the delay of `train` polled at `recorded_at`, see [`crate::ItaloApi::now()`]
+    pub fn from_realtime(train: &TrainRealtime, recorded_at: DateTime<Utc>) -> Self {
+        Self {
+            train_number: train.train_schedule().train_number().clone(),
+            date: recorded_at.with_timezone(&Rome).date_naive(),
+            recorded_at,
+            delay_minutes: *train.train_schedule().disruption().delay_amount(),
+        }
+    }
+}
+
+/// Storage for [`DelayRecord`]s, implement it on top of a file or a database to keep the history across runs
+pub trait HistoryStore {
+    /// Persist a record
+    fn save(&mut self, record: DelayRecord) -> anyhow::Result<()>;
+
+    /// Every record of `train_number`
+    fn load(&self, train_number: &str) -> anyhow::Result<Vec<DelayRecord>>;
+}
+
+/// In memory store
+impl HistoryStore for Vec<DelayRecord> {
+    fn save(&mut self, record: DelayRecord) -> anyhow::Result<()> {
+        self.push(record);
+        Ok(())
+    }
+
+    fn load(&self, train_number: &str) -> anyhow::Result<Vec<DelayRecord>> {
+        Ok(self
+            .iter()
+            .filter(|record| record.train_number == train_number)
+            .cloned()
+            .collect())
+    }
+}
+
+/// On-time performance of a train over the recorded days
+#[derive(Debug, Clone, PartialEq, Getters)]
+#[get = "pub"]
+pub struct DelayStats {
+    /// Number of days with at least one record
+    days: usize,
+
+    /// Mean of the daily delays (in minutes)
+    average_delay_minutes: f64,
+
+    /// Worst daily delay (in minutes)
+    max_delay_minutes: i32,
+}
+
+impl DelayStats {
+    /// Aggregate `records` of a single train: the last poll of each day is its daily delay.
+    ///
+    /// `None` without records
+    pub fn from_records(records: &[DelayRecord]) -> Option<Self> {
+        let mut daily = BTreeMap::new();
+        records.iter().for_each(|record| {
+            daily
+                .entry(record.date)
+                .and_modify(|last: &mut &DelayRecord| {
+                    if record.recorded_at >= last.recorded_at {
+                        *last = record;
+                    }
+                })
+                .or_insert(record);
+        });
+
+        let delays: Vec<_> = daily.values().map(|record| record.delay_minutes).collect();
+        Some(Self {
+            days: delays.len(),
+            average_delay_minutes: f64::from(delays.iter().sum::<i32>()) / delays.len() as f64,
+            max_delay_minutes: *delays.iter().max()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+
+    #[test]
+    fn daily_delay_stats() {
+        let train: TrainRealtime = serde_json::from_str(include_str!(
+            "../../tests/fixtures/train_realtime_8158.json"
+        ))
+        .unwrap();
+        let mut store = Vec::new();
+
+        let today = DelayRecord::from_realtime(
+            &train,
+            Utc.with_ymd_and_hms(2024, 7, 15, 10, 0, 0).unwrap(),
+        );
+        let yesterday = DelayRecord {
+            date: today.date - Duration::days(1),
+            recorded_at: today.recorded_at - Duration::days(1),
+            ..today.clone()
+        };
+        for (record, delay) in [(&yesterday, 12), (&yesterday, 20), (&today, 1), (&today, 4)] {
+            let mut record = record.clone();
+            record.recorded_at += Duration::minutes(delay.into());
+            record.delay_minutes = delay;
+            store.save(record).unwrap();
+        }
+
+        let records = store.load("8158").unwrap();
+        assert_eq!(records.len(), 4);
+        assert!(store.load("9914").unwrap().is_empty());
+
+        let stats = DelayStats::from_records(&records).unwrap();
+        assert_eq!(stats.days(), &2);
+        assert_eq!(stats.average_delay_minutes(), &12.);
+        assert_eq!(stats.max_delay_minutes(), &20);
+
+        assert_eq!(DelayStats::from_records(&[]), None);
+    }
+}
@@ -0,0 +1,104 @@
+use chrono::{DateTime, Utc};
+
+use crate::Journey;
+
+static ICS_DATE_TIME_PATTERN: &str = "%Y%m%dT%H%M%SZ";
+static MAX_LINE_OCTETS: usize = 75;
+
+fn format_time(val: DateTime<Utc>) -> String {
+    val.format(ICS_DATE_TIME_PATTERN).to_string()
+}
+
+fn escape_text(val: &str) -> String {
+    val.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Fold content lines longer than 75 octets as required by RFC 5545
+fn fold_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut octets = 0;
+    for char in line.chars() {
+        if octets + char.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            octets = 1;
+        }
+        octets += char.len_utf8();
+        folded.push(char);
+    }
+    folded + "\r\n"
+}
+
+impl Journey {
+    /// Export the journey as an iCalendar VEVENT spanning from the first departure to the last arrival
+    pub fn to_ics(&self, summary: &str) -> anyhow::Result<String> {
+        let start = self.departure_time()?;
+        let end = self.arrival_time()?;
+
+        let description = self
+            .segments()
+            .iter()
+            .map(|segment| {
+                Ok(format!(
+                    "Train {} {} - {}",
+                    segment.train_number(),
+                    format_time(segment.departure_time()?),
+                    format_time(segment.arrival_time()?)
+                ))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?
+            .join("\n");
+
+        let uid = self
+            .segments()
+            .iter()
+            .map(|segment| segment.train_number().as_str())
+            .collect::<Vec<_>>()
+            .join("-");
+
+        Ok([
+            "BEGIN:VCALENDAR".to_owned(),
+            "VERSION:2.0".to_owned(),
+            "PRODID:-//italo-api//EN".to_owned(),
+            "BEGIN:VEVENT".to_owned(),
+            format!("UID:{}-{}@italo-api", format_time(start), uid),
+            format!("DTSTAMP:{}", format_time(Utc::now())),
+            format!("DTSTART:{}", format_time(start)),
+            format!("DTEND:{}", format_time(end)),
+            format!("SUMMARY:{}", escape_text(summary)),
+            format!("DESCRIPTION:{}", escape_text(&description)),
+            "END:VEVENT".to_owned(),
+            "END:VCALENDAR".to_owned(),
+        ]
+        .iter()
+        .map(|line| fold_line(line))
+        .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::JourneyResults;
+
+    use super::*;
+
+    #[test]
+    fn journey_to_ics() {
+        let results: JourneyResults =
+            serde_json::from_str(include_str!("../../tests/fixtures/journey_results.json"))
+                .unwrap();
+        let ics = results.solutions()[0].journeys()[1]
+            .to_ics("Napoli, Milano")
+            .unwrap();
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.contains("DTSTART:20240715T100000Z\r\n"));
+        assert!(ics.contains("DTEND:20240715T150000Z\r\n"));
+        assert!(ics.contains("SUMMARY:Napoli\\, Milano\r\n"));
+        assert!(ics.contains("Train 9914"));
+        assert!(ics.contains("Train 9950"));
+        assert!(ics.split("\r\n").all(|line| line.len() <= MAX_LINE_OCTETS));
+    }
+}
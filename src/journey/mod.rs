@@ -1,34 +1,28 @@
-use crate::Station;
+use crate::time::{extract_utc_time, rome_day_bounds, ItaloDateTime};
+use crate::{de::italian_decimal, error::retain_decodable, ParseWarning, ServiceType, Station};
 use anyhow::anyhow;
 use anyhow::Context;
 use anyhow::Ok;
 use chrono::NaiveDate;
-use chrono::NaiveDateTime;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::{Europe::Rome, Tz};
 use derive_new::new;
 use getset::Getters;
 use getset::Setters;
 use serde::Deserialize;
 use serde::Serialize;
+use std::collections::HashSet;
 
 static DATE_TIME_PATTERN: &str = "/Date(%s000+0000)/";
-pub type RoundTrip = (bool, Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+static BOOKING_URL: &str = "https://www.italotreno.com/it/acquista-biglietto";
+/// Average emissions per passenger-km of an high speed train
+static TRAIN_CO2_GRAMS_PER_KM: f64 = 14.;
+/// Average emissions per passenger-km of a coach
+static BUS_CO2_GRAMS_PER_KM: f64 = 27.;
+/// Wording of the notices published on strike days
+static STRIKE_KEYWORDS: [&str; 2] = ["scioper", "strike"];
 
-fn extract_utc_time(val: &str) -> anyhow::Result<DateTime<Utc>> {
-    DateTime::from_timestamp(
-        val.split_once('(')
-            .context("Failed to extract dateTime")?
-            .1
-            .split_once('+')
-            .context("Failed to extract dateTime")?
-            .0
-            .parse::<i64>()
-            .expect("Timestamp conversion failed")
-            / 1000,
-        0,
-    )
-    .context("invalid timestamp")
-}
+pub type RoundTrip = (bool, Option<DateTime<Utc>>, Option<DateTime<Utc>>);
 
 #[derive(Serialize, Debug, new)]
 #[serde(rename_all = "PascalCase")]
@@ -39,7 +33,10 @@ pub struct InternalJourneyRequest<'a> {
 }
 
 /// Input object for [crate::ItaloApi::find_journeys]
-#[derive(Serialize, Debug, Setters)]
+///
+/// Adults, children and seniors occupy a seat, infants travel on the lap of an adult or senior
+/// and do not count towards seat availability. See [`Self::validate()`] for the accepted mixes.
+#[derive(Serialize, Debug, Clone, Setters)]
 #[serde(rename_all = "PascalCase")]
 #[set = "pub"]
 pub struct JourneyRequest {
@@ -86,6 +83,19 @@ pub struct JourneyRequest {
     #[getset(skip)]
     #[serde(skip_serializing_if = "Option::is_none")]
     round_trip_interval_end_date_time: Option<String>,
+
+    /// Set the seat preferences sent along with the search.
+    ///
+    /// GetAvailableTrains is not known to filter on them: availability and fares are the same
+    /// with or without, they are carried for the booking step of the session.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seat_preferences: Option<SeatPreferences>,
+
+    /// Drop returned journeys departing before this moment.
+    ///
+    /// Unlike the interval start it is not sent to the server, it filters the journeys it returns.
+    #[serde(skip)]
+    min_departure: Option<DateTime<Utc>>,
 }
 
 impl Default for JourneyRequest {
@@ -105,11 +115,42 @@ impl Default for JourneyRequest {
             round_trip: false,
             round_trip_interval_start_date_time: Default::default(),
             round_trip_interval_end_date_time: Default::default(),
+            seat_preferences: None,
+            min_departure: None,
         }
     }
 }
 
 impl JourneyRequest {
+    /// One way search for one adult departing from `from` to `to` in the `window` following `depart_after`
+    pub fn one_way(
+        from: Station,
+        to: Station,
+        depart_after: DateTime<Utc>,
+        window: Duration,
+    ) -> JourneyRequest {
+        let mut request = JourneyRequest::default();
+        request
+            .set_departure_station(from)
+            .set_arrival_station(to)
+            .set_interval_start_date_time(depart_after)
+            .set_interval_end_date_time(depart_after + window);
+        request
+    }
+
+    /// One way search for one adult departing from `from` to `to` in the `hours` following `now`, for
+    /// "what's coming up" queries. Pass [`crate::ItaloApi::now()`] to honour the configured clock
+    pub fn next_hours(
+        from: Station,
+        to: Station,
+        now: DateTime<Utc>,
+        hours: i64,
+    ) -> JourneyRequest {
+        let mut request = Self::one_way(from, to, now, Duration::hours(hours));
+        request.set_override_interval_time_restriction(false);
+        request
+    }
+
     /// Set the departure station for the journey search
     pub fn set_departure_station(&mut self, val: Station) -> &mut Self {
         self.departure_station = val.code().to_owned();
@@ -134,6 +175,59 @@ impl JourneyRequest {
         self
     }
 
+    /// Search the whole italian civil day `date`, from midnight to midnight in Europe/Rome.
+    ///
+    /// The day lasts 23 or 25 hours on daylight saving changes
+    pub fn set_day_rome(&mut self, date: NaiveDate) -> anyhow::Result<&mut Self> {
+        let (start, end) = rome_day_bounds(date)?;
+        Ok(self.set_search_window(start, end))
+    }
+
+    /// Search over a window spanning several days, results are grouped by day in [`JourneyResults::solutions()`]
+    pub fn set_search_window(&mut self, start: DateTime<Utc>, end: DateTime<Utc>) -> &mut Self {
+        self.set_interval_start_date_time(start)
+            .set_interval_end_date_time(end)
+    }
+
+    /// Body posted by [`crate::ItaloApi::find_journeys()`] for this request, without the session signature.
+    ///
+    /// Handy to reproduce a search with curl or attach it to a bug report
+    pub fn to_request_json(&self) -> serde_json::Value {
+        let mut body = serde_json::to_value(InternalJourneyRequest::new(
+            Default::default(),
+            crate::SOURCE_SYSTEM,
+            self,
+        ))
+        .unwrap_or_default();
+        if let Some(body) = body.as_object_mut() {
+            body.remove("Signature");
+        }
+        body
+    }
+
+    pub(crate) fn min_departure(&self) -> Option<DateTime<Utc>> {
+        self.min_departure
+    }
+
+    /// Seats needed by the passengers, infants excluded
+    pub fn seats_required(&self) -> u16 {
+        u16::from(self.adult_number) + u16::from(self.child_number) + u16::from(self.senior_number)
+    }
+
+    /// Check the passenger mix: at least one seated passenger is required and infants
+    /// must be accompanied by at least one adult or senior
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.seats_required() == 0 {
+            return Err(anyhow!("At least one adult, child or senior is required"));
+        }
+        if self.infant_number > 0 && self.adult_number == 0 && self.senior_number == 0 {
+            return Err(anyhow!(
+                "Infants must travel with at least one adult or senior"
+            ));
+        }
+        Ok(())
+    }
+
     /// Set data to search for round trip solutions
     pub fn set_round_trip(&mut self, val: RoundTrip) -> anyhow::Result<&mut Self> {
         match val {
@@ -159,18 +253,348 @@ impl JourneyRequest {
     }
 }
 
+/// Seat preferences of a [`JourneyRequest`]
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq, Setters)]
+#[serde(rename_all = "PascalCase")]
+#[set = "pub"]
+pub struct SeatPreferences {
+    /// Set the preferred seat position
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position: Option<SeatPosition>,
+
+    /// Set whether to sit in the quiet area (Ambiente Silenzio)
+    quiet_area: bool,
+}
+
+/// Position of the seat in the coach
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeatPosition {
+    /// Next to the window
+    Window,
+
+    /// Next to the aisle
+    Aisle,
+}
+
 /// Output object for [crate::ItaloApi::find_journeys]
 #[derive(Deserialize, Debug, Getters)]
+#[cfg_attr(feature = "binary-cache", derive(serde::Serialize))]
 #[serde(rename_all = "PascalCase")]
 #[get = "pub"]
 pub struct JourneyResults {
     /// Array of alternative solutions
     #[serde(rename(deserialize = "JourneyDateMarkets"))]
     solutions: Vec<JourneysSolution>,
+
+    /// Service notices read from a `Messages` list of the search response.
+    ///
+    /// Speculative: no captured response carries that key yet, so expect it empty until it is confirmed
+    #[serde(default, rename(deserialize = "Messages"))]
+    notices: Vec<String>,
+}
+
+impl JourneyResults {
+    /// Journeys of every day in server order
+    pub fn journeys(&self) -> impl Iterator<Item = &Journey> {
+        self.solutions
+            .iter()
+            .flat_map(|solution| &solution.journeys)
+    }
+
+    /// Take the journeys of every day in server order
+    pub fn into_journeys(self) -> impl Iterator<Item = Journey> {
+        self.solutions
+            .into_iter()
+            .flat_map(|solution| solution.journeys)
+    }
+
+    /// Distinct train numbers of every segment, in server order.
+    ///
+    /// Bus segments without a number are skipped, pass the others to [`crate::ItaloApi::train_realtime()`]
+    pub fn train_numbers(&self) -> Vec<String> {
+        let mut seen = HashSet::new();
+        self.journeys()
+            .flat_map(|journey| &journey.segments)
+            .map(|segment| &segment.train_number)
+            .filter(|number| !number.is_empty() && seen.insert(number.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    /// Notices mentioning a strike (sciopero, scioperi or strike)
+    pub fn strike_notices(&self) -> Vec<&str> {
+        self.notices
+            .iter()
+            .filter(|notice| {
+                let notice = notice.to_lowercase();
+                STRIKE_KEYWORDS
+                    .iter()
+                    .any(|keyword| notice.contains(keyword))
+            })
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Only the guaranteed services run because of a strike: expect fewer journeys than usual.
+    ///
+    /// Detected from [`Self::strike_notices()`], false when italo sends no notice. As the notices key is
+    /// unconfirmed, a false value does not rule a strike out
+    pub fn is_reduced_service(&self) -> bool {
+        !self.strike_notices().is_empty()
+    }
+
+    /// Drop journeys departing before `min_departure`, journeys with unparseable times are kept
+    pub(crate) fn retain_departing_after(&mut self, min_departure: DateTime<Utc>) {
+        self.solutions.iter_mut().for_each(|solution| {
+            solution.journeys.retain(|journey| {
+                journey
+                    .departure_time()
+                    .map_or(true, |departure| departure >= min_departure)
+            })
+        });
+    }
+
+    /// Merge the results of several searches: journeys of the same day are grouped and listed once,
+    /// days and journeys are sorted by departure time
+    pub(crate) fn merge(results: impl IntoIterator<Item = JourneyResults>) -> JourneyResults {
+        let mut merged = JourneyResults {
+            solutions: Vec::new(),
+            notices: Vec::new(),
+        };
+        for result in results {
+            for notice in result.notices {
+                if !merged.notices.contains(&notice) {
+                    merged.notices.push(notice);
+                }
+            }
+            for solution in result.solutions {
+                match merged
+                    .solutions
+                    .iter_mut()
+                    .find(|elem| elem.departure_date == solution.departure_date)
+                {
+                    Some(elem) => elem.journeys.extend(solution.journeys),
+                    None => merged.solutions.push(solution),
+                }
+            }
+        }
+
+        merged.dedup();
+        merged.solutions.sort_by_cached_key(|solution| {
+            let date = solution.departure_date().ok();
+            (date.is_none(), date)
+        });
+        merged.solutions.iter_mut().for_each(|solution| {
+            solution.journeys.sort_by_cached_key(|journey| {
+                let departure = journey.departure_time().ok();
+                (departure.is_none(), departure)
+            })
+        });
+        merged
+    }
+
+    /// Drop journeys already listed, keeping the first occurrence.
+    ///
+    /// Two journeys are the same when every segment has the same train number and departure time,
+    /// which happens when overlapping windows or boundary days return the same trains twice
+    pub fn dedup(&mut self) {
+        let mut seen = HashSet::new();
+        self.solutions.iter_mut().for_each(|solution| {
+            solution
+                .journeys
+                .retain(|journey| seen.insert(journey.identity()))
+        });
+    }
+
+    /// Lowest single passenger price of a whole journey, the "from X" price of the search.
+    ///
+    /// Multi-segment journeys add up the cheapest fare of each leg, see [`Journey::cheapest_price()`].
+    /// `None` when no journey has seats left on every segment
+    pub fn min_fare(&self) -> Option<f64> {
+        self.journeys()
+            .filter_map(Journey::cheapest_price)
+            .min_by(f64::total_cmp)
+    }
+
+    /// One row per journey with the data shown by list tables and spreadsheet exports, in server order.
+    ///
+    /// Journeys with unparseable times or dates are left out
+    pub fn to_options(&self) -> Vec<JourneyOption> {
+        self.solutions
+            .iter()
+            .filter_map(|solution| Some((solution.departure_date().ok()?, &solution.journeys)))
+            .flat_map(|(date, journeys)| journeys.iter().map(move |journey| (date, journey)))
+            .filter_map(|(date, journey)| {
+                let depart = journey.departure_time().ok()?;
+                let arrive = journey.arrival_time().ok()?;
+                Some(JourneyOption {
+                    date,
+                    depart,
+                    arrive,
+                    duration: arrive - depart,
+                    transfers: journey.segments.len().saturating_sub(1),
+                    cheapest_fare: journey.cheapest_price(),
+                    train_numbers: journey
+                        .segments
+                        .iter()
+                        .map(|segment| segment.train_number.clone())
+                        .collect(),
+                })
+            })
+            .collect()
+    }
+
+    /// Journey with the lowest [`Journey::value_score()`], `None` when no journey can be scored.
+    ///
+    /// The criteria are normalized against these results first, see [`ValueWeights::normalized_to()`]
+    pub fn best_value(&self, weights: &ValueWeights) -> Option<&Journey> {
+        let weights = weights.normalized_to(self);
+        self.journeys()
+            .map(|journey| (journey.value_score(&weights), journey))
+            .filter(|(score, _)| score.is_finite())
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, journey)| journey)
+    }
+
+    /// Journeys of every day, fastest first. Journeys with unparseable times come last.
+    pub fn sorted_by_duration(&self) -> Vec<&Journey> {
+        let mut journeys: Vec<_> = self.journeys().collect();
+        journeys.sort_by_cached_key(|journey| {
+            let duration = journey.total_duration().ok();
+            (duration.is_none(), duration)
+        });
+        journeys
+    }
+
+    /// Journeys of every day, cheapest first. Journeys without available fares come last.
+    pub fn sorted_by_price(&self) -> Vec<&Journey> {
+        let mut journeys: Vec<_> = self.journeys().collect();
+        journeys.sort_by(|a, b| match (a.cheapest_price(), b.cheapest_price()) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            (a, b) => a.is_none().cmp(&b.is_none()),
+        });
+        journeys
+    }
+}
+
+/// Weights of [`Journey::value_score()`], the relative importance of price, duration and changes.
+///
+/// Once [`Self::normalized_to()`] a set of results every criterion spans 0 (best) to 1 (worst) among them,
+/// so the weights compare like for like. Otherwise the criteria are taken in euros, hours and changes.
+/// The defaults weigh price and duration alike and a change half as much
+#[derive(Debug, Clone, Copy, PartialEq, Getters, Setters)]
+#[getset(get = "pub", set = "pub")]
+pub struct ValueWeights {
+    /// Weight of the price for a single passenger
+    price: f64,
+
+    /// Weight of the door to door duration
+    duration: f64,
+
+    /// Weight of the number of changes
+    transfers: f64,
+
+    /// Lowest value and range of each criterion, see [`Self::normalized_to()`]
+    #[getset(skip)]
+    bounds: Option<[(f64, f64); 3]>,
+}
+
+impl Default for ValueWeights {
+    fn default() -> Self {
+        Self {
+            price: 1.,
+            duration: 1.,
+            transfers: 0.5,
+            bounds: None,
+        }
+    }
+}
+
+impl ValueWeights {
+    /// Same weights with the criteria min–max normalized against the scoreable journeys of `results`
+    pub fn normalized_to(&self, results: &JourneyResults) -> Self {
+        let mut bounds = [(f64::INFINITY, f64::NEG_INFINITY); 3];
+        for criteria in results.journeys().filter_map(Journey::value_criteria) {
+            for ((min, max), val) in bounds.iter_mut().zip(criteria) {
+                *min = min.min(val);
+                *max = max.max(val);
+            }
+        }
+
+        Self {
+            bounds: Some(bounds.map(|(min, max)| (min, max - min))),
+            ..*self
+        }
+    }
+
+    fn score(&self, criteria: [f64; 3]) -> f64 {
+        let criteria = match self.bounds {
+            Some(bounds) => {
+                let mut normalized = [0.; 3];
+                for ((val, (min, range)), normalized) in
+                    criteria.iter().zip(bounds).zip(&mut normalized)
+                {
+                    if range > 0. {
+                        *normalized = ((val - min) / range).clamp(0., 1.);
+                    }
+                }
+                normalized
+            }
+            None => criteria,
+        };
+
+        self.price * criteria[0] + self.duration * criteria[1] + self.transfers * criteria[2]
+    }
+}
+
+/// Flat view of a journey, see [`JourneyResults::to_options()`]
+#[derive(Debug, Clone, PartialEq, Getters)]
+#[get = "pub"]
+pub struct JourneyOption {
+    /// Date of the search results listing the journey
+    date: NaiveDate,
+
+    /// Departure time of the first segment
+    depart: DateTime<Utc>,
+
+    /// Arrival time of the last segment
+    arrive: DateTime<Utc>,
+
+    /// Door to door duration
+    duration: Duration,
+
+    /// Number of changes
+    transfers: usize,
+
+    /// Price for a single passenger, see [`Journey::cheapest_price()`]
+    cheapest_fare: Option<f64>,
+
+    /// Italo ID of every segment, empty for bus legs
+    train_numbers: Vec<String>,
+}
+
+/// Parse a search response dropping the journeys that fail to decode instead of failing the whole search
+pub(crate) fn parse_journey_results_lenient(
+    body: &str,
+) -> anyhow::Result<(JourneyResults, Vec<ParseWarning>)> {
+    let mut value: serde_json::Value = serde_json::from_str(body)?;
+    let mut warnings = Vec::new();
+
+    if let Some(serde_json::Value::Array(markets)) = value.get_mut("JourneyDateMarkets") {
+        for (index, market) in markets.iter_mut().enumerate() {
+            if let Some(serde_json::Value::Array(journeys)) = market.get_mut("Journeys") {
+                let list = format!("JourneyDateMarkets[{index}].Journeys");
+                retain_decodable::<Journey>(journeys, &list, &mut warnings);
+            }
+        }
+    }
+
+    Ok((serde_json::from_value(value)?, warnings))
 }
 
 /// Array of journeys for the date specified by [JourneysSolution::departure_date]
 #[derive(Deserialize, Debug, Getters)]
+#[cfg_attr(feature = "binary-cache", derive(serde::Serialize))]
 #[serde(rename_all = "PascalCase")]
 #[get = "pub"]
 pub struct JourneysSolution {
@@ -185,80 +609,611 @@ impl JourneysSolution {
     /// Date on which the journeys are valid
     pub fn departure_date(&self) -> anyhow::Result<NaiveDate> {
         //Something is wrong on italo side
-        Ok(NaiveDate::from(
-            NaiveDateTime::from_timestamp_millis(
-                self.departure_date
-                    .split_once('(')
-                    .context("Failed to extract date")?
-                    .1
-                    .split_once('+')
-                    .context("Failed to extract date")?
-                    .0
-                    .parse()
-                    .context("Failed to parse timestamp")?,
-            )
-            .context("Failed to parse Date")?,
-        ))
+        Ok(extract_utc_time(&self.departure_date)?.date_naive())
     }
 }
 
 /// Describes a journey using one or more trains
 #[derive(Deserialize, Debug, Getters)]
+#[cfg_attr(feature = "binary-cache", derive(serde::Serialize))]
 #[serde(rename_all = "PascalCase")]
 #[get = "pub"]
 pub struct Journey {
     /// Different parts by which the journey has been divided
     segments: Vec<JourneySegment>,
+
+    /// Environmental data read from a `Sustainability` object of the journey.
+    ///
+    /// Speculative: no captured search response carries that key yet, so expect `None` and rely on
+    /// [`Journey::estimated_co2_grams()`]
+    #[serde(default)]
+    sustainability: Option<Sustainability>,
+}
+
+/// Environmental data of a journey, in the speculative `Sustainability` shape (`Co2Grams`, `CarCo2Grams`).
+///
+/// Not confirmed against a real GetAvailableTrains response
+#[derive(Deserialize, Debug, Clone, Getters)]
+#[cfg_attr(feature = "binary-cache", derive(serde::Serialize))]
+#[serde(rename_all = "PascalCase")]
+#[get = "pub"]
+pub struct Sustainability {
+    /// CO2 emitted per passenger (in grams)
+    #[serde(rename(deserialize = "Co2Grams"))]
+    co2_grams: f64,
+
+    /// CO2 emitted per passenger by car on the same route (in grams)
+    #[serde(default, rename(deserialize = "CarCo2Grams"))]
+    car_co2_grams: Option<f64>,
+}
+
+impl Journey {
+    /// Train number and departure time of every segment, equal for listings of the same journey
+    pub(crate) fn identity(&self) -> Vec<(String, String)> {
+        self.segments
+            .iter()
+            .map(|segment| {
+                (
+                    segment.train_number.clone(),
+                    segment.departure_time.to_string(),
+                )
+            })
+            .collect()
+    }
+
+    /// Italo IDs of the first departure and the last arrival stations
+    pub(crate) fn endpoints(&self) -> Option<(&str, &str)> {
+        Some((
+            self.segments
+                .first()?
+                .stops
+                .first()?
+                .departure_station
+                .as_str(),
+            self.segments.last()?.stops.last()?.arrival_station.as_str(),
+        ))
+    }
+
+    /// Departure time of the first segment
+    pub fn departure_time(&self) -> anyhow::Result<DateTime<Utc>> {
+        self.segments
+            .first()
+            .context("Journey without segments")?
+            .departure_time()
+    }
+
+    /// Arrival time of the last segment
+    pub fn arrival_time(&self) -> anyhow::Result<DateTime<Utc>> {
+        self.segments
+            .last()
+            .context("Journey without segments")?
+            .arrival_time()
+    }
+
+    /// Time from the first departure to the last arrival
+    pub fn total_duration(&self) -> anyhow::Result<Duration> {
+        Ok(self.arrival_time()? - self.departure_time()?)
+    }
+
+    /// The journey arrives on a later italian calendar day than it departs
+    pub fn is_overnight(&self) -> bool {
+        self.departure_time_in(Rome)
+            .ok()
+            .zip(self.arrival_time_in(Rome).ok())
+            .is_some_and(|(departure, arrival)| arrival.date_naive() > departure.date_naive())
+    }
+
+    /// CO2 emitted per passenger (in grams).
+    ///
+    /// A rough estimate computed from the great-circle distance between the stops, looked up in `stations`,
+    /// and average emission factors for high speed trains and coaches. `None` when a stop has no coordinates.
+    ///
+    /// The speculative [`Self::sustainability()`] figure takes precedence should italo ever send it
+    pub fn estimated_co2_grams(&self, stations: &[Station]) -> Option<f64> {
+        if let Some(sustainability) = &self.sustainability {
+            return Some(sustainability.co2_grams);
+        }
+
+        let station = |code: &str| stations.iter().find(|station| station.code() == code);
+        self.segments
+            .iter()
+            .flat_map(|segment| {
+                let factor = match segment.mode() {
+                    SegmentMode::Train => TRAIN_CO2_GRAMS_PER_KM,
+                    SegmentMode::Bus => BUS_CO2_GRAMS_PER_KM,
+                };
+                segment.stops.iter().map(move |stop| {
+                    let to = station(&stop.arrival_station)?;
+                    let distance = station(&stop.departure_station)?
+                        .distance_km((*to.latitude())?, (*to.longitude())?)?;
+                    Some(distance * factor)
+                })
+            })
+            .sum()
+    }
+
+    /// Human readable itinerary with times in the `tz` timezone, one line per segment and per change:
+    ///
+    /// ```text
+    /// 12:00 NAC -> 13:00 RMT  Italo 9914
+    /// Change at RMT (30 min)
+    /// 13:30 RMT -> 17:00 MC_  Italo 9950
+    /// ```
+    ///
+    /// Stations are shown by Italo ID, arrivals on a later day are marked with `(+N)`
+    pub fn format_itinerary(&self, tz: Tz) -> String {
+        let time = |val: anyhow::Result<DateTime<Utc>>| {
+            val.map(|val| val.with_timezone(&tz).format("%H:%M").to_string())
+                .unwrap_or_else(|_| "--:--".to_owned())
+        };
+        let first_day = self.departure_time_in(tz).map(|val| val.date_naive()).ok();
+
+        let mut lines = Vec::new();
+        for (index, segment) in self.segments.iter().enumerate() {
+            let from = segment
+                .stops
+                .first()
+                .map_or("?", |stop| stop.departure_station.as_str());
+            let to = segment
+                .stops
+                .last()
+                .map_or("?", |stop| stop.arrival_station.as_str());
+
+            if let Some(previous) = index.checked_sub(1).map(|index| &self.segments[index]) {
+                let wait = segment
+                    .departure_time()
+                    .ok()
+                    .zip(previous.arrival_time().ok())
+                    .map(|(departure, arrival)| {
+                        format!(" ({} min)", (departure - arrival).num_minutes())
+                    })
+                    .unwrap_or_default();
+                lines.push(format!("Change at {from}{wait}"));
+            }
+
+            let days_later = first_day
+                .zip(segment.arrival_time_in(tz).ok())
+                .map(|(first, arrival)| (arrival.date_naive() - first).num_days())
+                .filter(|days| *days > 0)
+                .map(|days| format!(" (+{days})"))
+                .unwrap_or_default();
+            let vehicle = match segment.mode() {
+                SegmentMode::Train => format!("Italo {}", segment.train_number),
+                SegmentMode::Bus => "Italobus".to_owned(),
+            };
+            lines.push(format!(
+                "{} {from} -> {}{days_later} {to}  {vehicle}",
+                time(segment.departure_time()),
+                time(segment.arrival_time()),
+            ));
+        }
+        lines.join("\n")
+    }
+
+    /// Link to the italotreno.com search for this journey, to hand the booking over to the official site.
+    ///
+    /// The URL carries the stations, the italian departure date and time and the passengers of `request`:
+    /// it reproduces the search, no seat is held since the crate does not book. The stations of `request`
+    /// are used when the journey has no stops.
+    ///
+    /// The page path and the query keys are unconfirmed: they are a best-effort guess at the site deep link
+    /// and were never checked against italotreno.com
+    pub fn booking_url(&self, request: &JourneyRequest) -> String {
+        let (from, to) = self.endpoints().unwrap_or((
+            request.departure_station.as_str(),
+            request.arrival_station.as_str(),
+        ));
+        let mut params = vec![("from", from.to_owned()), ("to", to.to_owned())];
+        if let Result::Ok(departure) = self.departure_time_in(Rome) {
+            params.push(("date", departure.format("%Y-%m-%d").to_string()));
+            params.push(("time", departure.format("%H:%M").to_string()));
+        }
+        params.extend([
+            ("adults", request.adult_number.to_string()),
+            ("children", request.child_number.to_string()),
+            ("infants", request.infant_number.to_string()),
+            ("seniors", request.senior_number.to_string()),
+        ]);
+
+        reqwest::Url::parse_with_params(BOOKING_URL, params)
+            .expect("BOOKING_URL is a valid URL")
+            .into()
+    }
+
+    /// Changes between consecutive segments, empty for direct journeys.
+    ///
+    /// Platforms are not part of the search response: they are always `None` here and can only be
+    /// learned from [`crate::ItaloApi::train_realtime()`] close to departure. Unparseable times are `None` too
+    pub fn transfers(&self) -> Vec<TransferInfo> {
+        self.segments
+            .windows(2)
+            .map(|pair| {
+                let (inbound, onward) = (&pair[0], &pair[1]);
+                TransferInfo {
+                    station: inbound
+                        .stops
+                        .last()
+                        .map(|stop| stop.arrival_station.clone())
+                        .or_else(|| {
+                            onward
+                                .stops
+                                .first()
+                                .map(|stop| stop.departure_station.clone())
+                        })
+                        .unwrap_or_default(),
+                    inbound_train: inbound.train_number.clone(),
+                    onward_train: onward.train_number.clone(),
+                    arrival_time: inbound.arrival_time().ok(),
+                    departure_time: onward.departure_time().ok(),
+                    arrival_platform: None,
+                    departure_platform: None,
+                }
+            })
+            .collect()
+    }
+
+    /// Every segment has at least one fare with seats left
+    pub fn has_availability(&self) -> bool {
+        !self.segments.is_empty()
+            && self
+                .segments
+                .iter()
+                .all(|segment| segment.fares.iter().any(Fare::is_available))
+    }
+
+    /// Total price for the passengers of `request`.
+    ///
+    /// Every seated passenger (adults, children and seniors) pays the cheapest available fare of each segment,
+    /// infants travel for free. `None` when a segment has no available fare.
+    pub fn total_price(&self, request: &JourneyRequest) -> Option<f64> {
+        Some(self.cheapest_price()? * f64::from(request.seats_required()))
+    }
+
+    /// Every segment has a single fare with enough seats left for the passengers of `request`, infants excluded
+    pub fn has_seats_for(&self, request: &JourneyRequest) -> bool {
+        let seats = request.seats_required();
+        !self.segments.is_empty()
+            && self.segments.iter().all(|segment| {
+                segment
+                    .fares
+                    .iter()
+                    .any(|fare| fare.available_count >= seats)
+            })
+    }
+
+    /// Cost of the journey weighing price, duration and changes with `weights`, the lower the better.
+    ///
+    /// Use weights [`ValueWeights::normalized_to()`] the results to compare journeys, as
+    /// [`JourneyResults::best_value()`] does. Infinite when the journey has no available fare or unparseable times
+    pub fn value_score(&self, weights: &ValueWeights) -> f64 {
+        self.value_criteria()
+            .map_or(f64::INFINITY, |criteria| weights.score(criteria))
+    }
+
+    /// Price (in euros), duration (in hours) and changes scored by [`Self::value_score()`]
+    fn value_criteria(&self) -> Option<[f64; 3]> {
+        let price = self.cheapest_price()?;
+        let hours = self.total_duration().ok()?.num_minutes() as f64 / 60.;
+        let transfers = self.segments.len().saturating_sub(1) as f64;
+        Some([price, hours, transfers])
+    }
+
+    /// Price for a single passenger using the cheapest available fare of each segment
+    pub fn cheapest_price(&self) -> Option<f64> {
+        self.segments
+            .iter()
+            .map(|segment| segment.cheapest_fare().map(|fare| fare.amount))
+            .sum()
+    }
+
+    /// The journey has not departed at the time `now` and seats are left, see [`crate::ItaloApi::now()`]
+    pub fn is_bookable(&self, now: DateTime<Utc>) -> bool {
+        self.has_availability() && self.departure_time().is_ok_and(|departure| departure > now)
+    }
+
+    /// Departure time of the first segment in the `tz` timezone
+    pub fn departure_time_in(&self, tz: Tz) -> anyhow::Result<DateTime<Tz>> {
+        Ok(self.departure_time()?.with_timezone(&tz))
+    }
+
+    /// Arrival time of the last segment in the `tz` timezone
+    pub fn arrival_time_in(&self, tz: Tz) -> anyhow::Result<DateTime<Tz>> {
+        Ok(self.arrival_time()?.with_timezone(&tz))
+    }
 }
 
 /// Single train journey
 #[derive(Deserialize, Debug, Getters)]
+#[cfg_attr(feature = "binary-cache", derive(serde::Serialize))]
 #[serde(rename_all = "PascalCase")]
 #[get = "pub"]
 pub struct JourneySegment {
     #[serde(rename(deserialize = "STD"))]
     #[getset(skip)]
-    departure_time: String,
+    departure_time: ItaloDateTime,
 
     #[serde(rename(deserialize = "STA"))]
     #[getset(skip)]
-    arrival_time: String,
+    arrival_time: ItaloDateTime,
 
-    /// Italo train ID
+    /// Italo train ID, empty for bus legs
+    #[serde(default)]
     train_number: String,
 
     /// Describes the train as direct
+    #[serde(default)]
     no_stop_train: bool,
 
     /// Train Stops
     #[serde(rename(deserialize = "Legs"))]
     stops: Vec<Stop>,
+
+    /// On board services (WiFi, power, cinema car, quiet area...), read from an `Amenities` list of the segment.
+    ///
+    /// Best-effort guess: no captured GetAvailableTrains response carries that key yet, so expect
+    /// this to be empty until the field is confirmed
+    #[serde(default)]
+    amenities: Vec<String>,
+
+    #[serde(default, rename(deserialize = "TrainType"))]
+    #[getset(skip)]
+    service_code: Option<String>,
+
+    /// Fares offered for the segment
+    #[serde(default)]
+    fares: Vec<Fare>,
+}
+
+/// Change of train at an intermediate station, see [`Journey::transfers()`].
+///
+/// Built from the search response alone, which carries no platform
+#[derive(Debug, Clone, Getters)]
+#[get = "pub"]
+pub struct TransferInfo {
+    /// Italo ID of the transfer station, empty when the segments have no stops
+    station: String,
+
+    /// Italo ID of the train arriving at the transfer station
+    inbound_train: String,
+
+    /// Italo ID of the train leaving the transfer station
+    onward_train: String,
+
+    /// Arrival time of the inbound train, `None` when unparseable
+    arrival_time: Option<DateTime<Utc>>,
+
+    /// Departure time of the onward train, `None` when unparseable
+    departure_time: Option<DateTime<Utc>>,
+
+    /// Arrival platform of the inbound train, always `None`: the search does not provide it,
+    /// look it up with [`crate::ItaloApi::train_realtime()`]
+    arrival_platform: Option<String>,
+
+    /// Departure platform of the onward train, always `None` for the same reason
+    departure_platform: Option<String>,
+}
+
+impl TransferInfo {
+    /// Time available to change train, `None` when a time is unparseable
+    pub fn connection_time(&self) -> Option<Duration> {
+        Some(self.departure_time? - self.arrival_time?)
+    }
+}
+
+/// Outcome of [`crate::ItaloApi::find_journeys_by_name()`]
+#[derive(Debug)]
+pub enum JourneyLookup {
+    /// Both stations resolved, carries the search results
+    Found(JourneyResults),
+
+    /// At least a name matches several stations, carries the candidates of both ends (a single one when resolved)
+    /// so the caller can pick them and search again with [`crate::ItaloApi::find_journeys()`]
+    Ambiguous {
+        departure: Vec<Station>,
+        arrival: Vec<Station>,
+    },
+
+    /// No station matches the name
+    NotFound(String),
+}
+
+/// Vehicle serving a journey segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentMode {
+    /// Italo train
+    Train,
+
+    /// Italobus connection
+    Bus,
+}
+
+/// Commercial brand of a journey segment, see [`JourneySegment::brand()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Brand {
+    /// Italo high-speed train
+    Italo,
+
+    /// Italobus connection
+    Italobus,
+
+    /// Train outside the Italo high-speed numbering (e.g. regional connections)
+    Other,
+}
+
+/// Fare offered for a journey segment
+#[derive(Deserialize, Debug, Clone, Getters)]
+#[cfg_attr(feature = "binary-cache", derive(serde::Serialize))]
+#[serde(rename_all = "PascalCase")]
+#[get = "pub"]
+pub struct Fare {
+    /// Travel class (e.g. Smart, Prima, Club Executive)
+    #[serde(default)]
+    class_of_service: String,
+
+    /// Commercial offer (e.g. Flex, Economy, Low Cost)
+    #[serde(default)]
+    product_class: String,
+
+    /// Price per passenger
+    #[serde(default, deserialize_with = "italian_decimal")]
+    amount: f64,
+
+    /// Seats left at this fare
+    #[serde(default)]
+    available_count: u16,
+
+    #[serde(default)]
+    #[getset(skip)]
+    refundable: Option<bool>,
+
+    #[serde(default)]
+    #[getset(skip)]
+    changeable: Option<bool>,
+}
+
+/// Refund and change conditions of a [`Fare`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Getters)]
+#[get = "pub"]
+pub struct FareRules {
+    /// The ticket can be refunded, possibly with a penalty
+    refundable: bool,
+
+    /// Date and time of the ticket can be changed, possibly paying a fee
+    changeable: bool,
+}
+
+impl Fare {
+    /// At least a seat is left at this fare
+    pub fn is_available(&self) -> bool {
+        self.available_count > 0
+    }
+
+    /// Refund and change conditions.
+    ///
+    /// The `Refundable`/`Changeable` flags are used when the response carries them, otherwise the rules
+    /// are derived from the published conditions of the offer: Flex can be changed and refunded,
+    /// Economy can only be changed, Low Cost neither. `None` for other offers
+    pub fn rules(&self) -> Option<FareRules> {
+        if let (Some(refundable), Some(changeable)) = (self.refundable, self.changeable) {
+            return Some(FareRules {
+                refundable,
+                changeable,
+            });
+        }
+
+        let offer = self.product_class.to_lowercase();
+        let (refundable, changeable) = if offer.contains("flex") {
+            (true, true)
+        } else if offer.contains("economy") {
+            (false, true)
+        } else if offer.contains("low cost") || offer.contains("lowcost") {
+            (false, false)
+        } else {
+            return None;
+        };
+        Some(FareRules {
+            refundable,
+            changeable,
+        })
+    }
 }
 
 impl JourneySegment {
+    /// Cheapest fare with seats left
+    pub fn cheapest_fare(&self) -> Option<&Fare> {
+        self.fares
+            .iter()
+            .filter(|fare| fare.is_available())
+            .min_by(|a, b| a.amount.total_cmp(&b.amount))
+    }
+
+    /// Vehicle serving the segment: Italobus legs have no train number or a bus `TrainType`
+    pub fn mode(&self) -> SegmentMode {
+        let is_bus = self
+            .service_code
+            .as_deref()
+            .is_some_and(|code| code.to_uppercase().contains("BUS"));
+
+        match is_bus || self.train_number.is_empty() {
+            true => SegmentMode::Bus,
+            false => SegmentMode::Train,
+        }
+    }
+
+    /// Service category, read from the `TrainType` code of the search response when present, see [`ServiceType`]
+    pub fn service_type(&self) -> ServiceType {
+        ServiceType::from(self.service_code.as_deref().unwrap_or_default())
+    }
+
+    /// Commercial brand of the segment.
+    ///
+    /// Bus segments are [`Brand::Italobus`]. Trains with a known high-speed rolling stock (AGV 575, EVO) or
+    /// numbered in the 8000-9999 range used by Italo high-speed services are [`Brand::Italo`], other
+    /// numeric IDs are [`Brand::Other`]. When the train number is not numeric the segment falls back to
+    /// [`Brand::Italo`], since every train of the Italo fleet is high-speed
+    pub fn brand(&self) -> Brand {
+        if self.mode() == SegmentMode::Bus {
+            return Brand::Italobus;
+        }
+        if !matches!(self.service_type(), ServiceType::Unknown(_)) {
+            return Brand::Italo;
+        }
+
+        match self.train_number.trim().parse::<u32>() {
+            Result::Ok(8000..=9999) | Err(_) => Brand::Italo,
+            Result::Ok(_) => Brand::Other,
+        }
+    }
+
+    /// Segment served by an Italo high-speed train, see [`JourneySegment::brand()`]
+    pub fn is_high_speed(&self) -> bool {
+        self.brand() == Brand::Italo
+    }
+
     /// Departure time
     pub fn departure_time(&self) -> anyhow::Result<DateTime<Utc>> {
-        extract_utc_time(&self.departure_time)
+        self.departure_time.to_utc_dated()
     }
 
     /// Arrival time
     pub fn arrival_time(&self) -> anyhow::Result<DateTime<Utc>> {
-        extract_utc_time(&self.arrival_time)
+        self.arrival_time.to_utc_dated()
+    }
+
+    /// Departure time in the `tz` timezone
+    pub fn departure_time_in(&self, tz: Tz) -> anyhow::Result<DateTime<Tz>> {
+        Ok(self.departure_time()?.with_timezone(&tz))
+    }
+
+    /// Arrival time in the `tz` timezone
+    pub fn arrival_time_in(&self, tz: Tz) -> anyhow::Result<DateTime<Tz>> {
+        Ok(self.arrival_time()?.with_timezone(&tz))
+    }
+
+    /// Departure time as milliseconds since the Unix epoch
+    pub fn departure_time_epoch_millis(&self) -> anyhow::Result<i64> {
+        self.departure_time.epoch_millis_dated()
+    }
+
+    /// Arrival time as milliseconds since the Unix epoch
+    pub fn arrival_time_epoch_millis(&self) -> anyhow::Result<i64> {
+        self.arrival_time.epoch_millis_dated()
     }
 }
 
 /// Train stop
 #[derive(Deserialize, Debug, Getters)]
+#[cfg_attr(feature = "binary-cache", derive(serde::Serialize))]
 #[serde(rename_all = "PascalCase")]
 #[get = "pub"]
 pub struct Stop {
     #[serde(rename(deserialize = "STD"))]
     #[getset(skip)]
-    departure_time: String,
+    departure_time: ItaloDateTime,
 
     #[serde(rename(deserialize = "STA"))]
     #[getset(skip)]
-    arrival_time: String,
+    arrival_time: ItaloDateTime,
 
     /// Departure station
     departure_station: String,
@@ -270,10 +1225,648 @@ pub struct Stop {
 impl Stop {
     /// Departure time
     pub fn departure_time(&self) -> anyhow::Result<DateTime<Utc>> {
-        extract_utc_time(&self.departure_time)
+        self.departure_time.to_utc_dated()
     }
     /// Arrival time
     pub fn arrival_time(&self) -> anyhow::Result<DateTime<Utc>> {
-        extract_utc_time(&self.arrival_time)
+        self.arrival_time.to_utc_dated()
+    }
+
+    /// Departure time in the `tz` timezone
+    pub fn departure_time_in(&self, tz: Tz) -> anyhow::Result<DateTime<Tz>> {
+        Ok(self.departure_time()?.with_timezone(&tz))
+    }
+
+    /// Arrival time in the `tz` timezone
+    pub fn arrival_time_in(&self, tz: Tz) -> anyhow::Result<DateTime<Tz>> {
+        Ok(self.arrival_time()?.with_timezone(&tz))
+    }
+
+    /// Departure time as milliseconds since the Unix epoch
+    pub fn departure_time_epoch_millis(&self) -> anyhow::Result<i64> {
+        self.departure_time.epoch_millis_dated()
+    }
+
+    /// Arrival time as milliseconds since the Unix epoch
+    pub fn arrival_time_epoch_millis(&self) -> anyhow::Result<i64> {
+        self.arrival_time.epoch_millis_dated()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::time::extract_epoch_millis;
+
+    use super::*;
+
+    fn fixture() -> JourneyResults {
+        serde_json::from_str(include_str!("../../tests/fixtures/journey_results.json")).unwrap()
+    }
+
+    #[test]
+    fn lenient_results_skip_broken_journeys() {
+        let mut body: serde_json::Value =
+            serde_json::from_str(include_str!("../../tests/fixtures/journey_results.json"))
+                .unwrap();
+        body["JourneyDateMarkets"][0]["Journeys"][0]["Segments"] = "broken".into();
+        assert!(serde_json::from_value::<JourneyResults>(body.clone()).is_err());
+
+        let (results, warnings) = parse_journey_results_lenient(&body.to_string()).unwrap();
+        assert_eq!(results.journeys().count(), fixture().journeys().count() - 1);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].list(), "JourneyDateMarkets[0].Journeys");
+        assert_eq!(warnings[0].index(), &0);
+    }
+
+    #[test]
+    fn merged_results() {
+        let multi_day: JourneyResults = serde_json::from_str(include_str!(
+            "../../tests/fixtures/journey_results_multi_day.json"
+        ))
+        .unwrap();
+        let days = multi_day.solutions().len();
+        let journeys = multi_day.journeys().count();
+
+        let merged = JourneyResults::merge([fixture(), multi_day, fixture()]);
+        let dates: Vec<_> = merged
+            .solutions()
+            .iter()
+            .map(|solution| solution.departure_date().unwrap())
+            .collect();
+        assert!(dates.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(dates.len() >= days);
+        assert!(merged.journeys().count() >= journeys);
+        assert_eq!(
+            JourneyResults::merge([fixture(), fixture()])
+                .journeys()
+                .count(),
+            fixture().journeys().count()
+        );
+
+        for solution in merged.solutions() {
+            let departures: Vec<_> = solution
+                .journeys()
+                .iter()
+                .map(|journey| journey.departure_time().unwrap())
+                .collect();
+            assert!(departures.windows(2).all(|pair| pair[0] <= pair[1]));
+        }
+    }
+
+    #[test]
+    fn multi_day_results() {
+        let results: JourneyResults = serde_json::from_str(include_str!(
+            "../../tests/fixtures/journey_results_multi_day.json"
+        ))
+        .unwrap();
+
+        let days: Vec<_> = results
+            .solutions()
+            .iter()
+            .map(|solution| solution.departure_date().unwrap())
+            .collect();
+        assert_eq!(
+            days,
+            vec![
+                NaiveDate::from_ymd_opt(2024, 7, 15).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 7, 16).unwrap()
+            ]
+        );
+        assert_eq!(results.journeys().count(), 3);
+
+        let date = |encoded: &str| {
+            serde_json::from_value::<JourneyResults>(serde_json::json!({
+                "JourneyDateMarkets": [{"DepartureDate": encoded, "Journeys": []}]
+            }))
+            .unwrap()
+            .solutions()[0]
+                .departure_date()
+        };
+        assert_eq!(
+            date("/Date(1721001600000)/").unwrap(),
+            NaiveDate::from_ymd_opt(2024, 7, 15).unwrap()
+        );
+        assert!(date("/Date()/").is_err());
+    }
+
+    #[test]
+    fn journey_co2() {
+        let station = |code: &str, lat, lon| {
+            Station::new(code.into(), Default::default(), Default::default())
+                .with_coordinates(Some(lat), Some(lon))
+        };
+        let stations = [
+            station("NAC", 40.8526, 14.2721),
+            station("RMT", 41.9010, 12.5016),
+            station("SMN", 43.7764, 11.2480),
+            station("MC_", 45.4862, 9.2046),
+        ];
+
+        let results = fixture();
+        let co2 = results.solutions()[0].journeys()[1]
+            .estimated_co2_grams(&stations)
+            .unwrap();
+        assert!((8000. ..11000.).contains(&co2), "{co2}");
+        assert_eq!(
+            results.solutions()[0].journeys()[1].estimated_co2_grams(&stations[..2]),
+            None
+        );
+
+        let mut raw: serde_json::Value =
+            serde_json::from_str(include_str!("../../tests/fixtures/journey_results.json"))
+                .unwrap();
+        raw["JourneyDateMarkets"][0]["Journeys"][0]["Sustainability"] =
+            serde_json::json!({ "Co2Grams": 5400.5, "CarCo2Grams": 98000 });
+        let results: JourneyResults = serde_json::from_value(raw).unwrap();
+        let journey = &results.solutions()[0].journeys()[0];
+        assert_eq!(journey.estimated_co2_grams(&[]), Some(5400.5));
+        assert_eq!(
+            journey.sustainability().as_ref().unwrap().car_co2_grams(),
+            &Some(98000.)
+        );
+    }
+
+    #[test]
+    fn itinerary_text() {
+        let results = fixture();
+        assert_eq!(
+            results.solutions()[0].journeys()[1].format_itinerary(Rome),
+            "12:00 NAC -> 13:00 RMT  Italo 9914\n\
+             Change at RMT (30 min)\n\
+             13:30 RMT -> 17:00 MC_  Italo 9950"
+        );
+        assert_eq!(
+            results.solutions()[0].journeys()[0].format_itinerary(Tz::UTC),
+            "09:00 NAC -> 13:30 MC_  Italo 8158"
+        );
+    }
+
+    #[test]
+    fn best_value_journey() {
+        let results = fixture();
+        let direct = &results.solutions()[0].journeys()[0];
+        let weights = ValueWeights::default();
+        assert!((direct.value_score(&weights) - (39.9 + 4.5)).abs() < 1e-9);
+
+        let normalized = weights.normalized_to(&results);
+        let scores: Vec<_> = results
+            .journeys()
+            .map(|journey| journey.value_score(&normalized))
+            .filter(|score| score.is_finite())
+            .collect();
+        assert!(scores.iter().all(|score| (0. ..=2.5).contains(score)));
+
+        let best = results.best_value(&weights).unwrap();
+        assert!(scores
+            .iter()
+            .all(|score| *score >= best.value_score(&normalized)));
+
+        let mut price_only = weights;
+        price_only.set_duration(0.).set_transfers(0.);
+        assert_eq!(
+            results.best_value(&price_only).unwrap().cheapest_price(),
+            results.sorted_by_price()[0].cheapest_price()
+        );
+    }
+
+    #[test]
+    fn strike_notices() {
+        assert!(!fixture().is_reduced_service());
+
+        let results: JourneyResults = serde_json::from_value(serde_json::json!({
+            "JourneyDateMarkets": [],
+            "Messages": [
+                "Nuovi servizi a bordo",
+                "Sciopero nazionale del 15 luglio: circoleranno solo i treni garantiti",
+                "Scioperi regionali in Lombardia"
+            ]
+        }))
+        .unwrap();
+        assert!(results.is_reduced_service());
+        assert_eq!(results.strike_notices().len(), 2);
+        assert!(results.strike_notices()[0].starts_with("Sciopero"));
+
+        let results: JourneyResults = serde_json::from_value(serde_json::json!({
+            "JourneyDateMarkets": [],
+            "Messages": ["Posto garantito", "Connessione garantita a Roma Termini"]
+        }))
+        .unwrap();
+        assert!(!results.is_reduced_service());
+    }
+
+    #[test]
+    fn options_table() {
+        let options = fixture().to_options();
+        assert_eq!(options.len(), fixture().journeys().count());
+
+        let direct = &options[0];
+        assert_eq!(
+            direct.date(),
+            &NaiveDate::from_ymd_opt(2024, 7, 15).unwrap()
+        );
+        assert_eq!(direct.train_numbers(), &["8158"]);
+        assert_eq!(direct.transfers(), &0);
+        assert_eq!(direct.duration(), &Duration::minutes(270));
+        assert_eq!(direct.cheapest_fare(), &Some(39.9));
+
+        let change = &options[1];
+        assert_eq!(change.train_numbers(), &["9914", "9950"]);
+        assert_eq!(change.transfers(), &1);
+        assert_eq!(change.duration(), &Duration::hours(5));
+    }
+
+    #[test]
+    fn distinct_train_numbers() {
+        let numbers = fixture().train_numbers();
+        assert_eq!(&numbers[..3], &["8158", "9914", "9950"]);
+        let distinct: HashSet<_> = numbers.iter().collect();
+        assert_eq!(distinct.len(), numbers.len());
+    }
+
+    #[test]
+    fn booking_link() {
+        let mut results = fixture();
+        let mut request = JourneyRequest::default();
+        request.set_adult_number(2).set_infant_number(1);
+
+        assert_eq!(
+            results.solutions()[0].journeys()[0].booking_url(&request),
+            "https://www.italotreno.com/it/acquista-biglietto?from=NAC&to=MC_&date=2024-07-15&time=11%3A00\
+             &adults=2&children=0&infants=1&seniors=0"
+        );
+
+        let journey = &mut results.solutions[0].journeys[0];
+        journey.segments.clear();
+        request.departure_station = "A&B".into();
+        request.arrival_station = "C D".into();
+        assert!(journey
+            .booking_url(&request)
+            .contains("?from=A%26B&to=C+D&adults=2"));
+    }
+
+    #[test]
+    fn journey_transfers() {
+        let mut results = fixture();
+
+        assert!(results.solutions()[0].journeys()[0].transfers().is_empty());
+
+        let transfers = results.solutions()[0].journeys()[1].transfers();
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].station(), "RMT");
+        assert_eq!(transfers[0].inbound_train(), "9914");
+        assert_eq!(transfers[0].onward_train(), "9950");
+        assert_eq!(transfers[0].connection_time(), Some(Duration::minutes(30)));
+        assert_eq!(transfers[0].arrival_platform(), &None);
+
+        results.solutions[0].journeys[1].segments[1].departure_time = "garbage".into();
+        let transfers = results.solutions()[0].journeys()[1].transfers();
+        assert_eq!(transfers[0].departure_time(), &None);
+        assert!(transfers[0].arrival_time().is_some());
+        assert_eq!(transfers[0].connection_time(), None);
+    }
+
+    #[test]
+    fn min_departure_filter() {
+        let mut results = fixture();
+        results.retain_departing_after(DateTime::from_timestamp(1721037600, 0).unwrap());
+
+        let trains: Vec<_> = results
+            .journeys()
+            .map(|journey| journey.segments()[0].train_number().as_str())
+            .collect();
+        assert_eq!(trains, vec!["9914"]);
+
+        let mut request = JourneyRequest::default();
+        request.set_min_departure(Some(Utc::now()));
+        assert!(request.to_request_json()["GetAvailableTrains"]
+            .get("MinDeparture")
+            .is_none());
+    }
+
+    #[test]
+    fn dedup_across_days() {
+        let mut raw: serde_json::Value = serde_json::from_str(include_str!(
+            "../../tests/fixtures/journey_results_multi_day.json"
+        ))
+        .unwrap();
+        let duplicate = raw["JourneyDateMarkets"][0]["Journeys"][0].clone();
+        raw["JourneyDateMarkets"][1]["Journeys"]
+            .as_array_mut()
+            .unwrap()
+            .push(duplicate);
+
+        let mut results: JourneyResults = serde_json::from_value(raw).unwrap();
+        assert_eq!(results.journeys().count(), 4);
+
+        results.dedup();
+        assert_eq!(results.journeys().count(), 3);
+        assert_eq!(results.solutions()[0].journeys().len(), 2);
+    }
+
+    #[test]
+    fn sorted_journeys() {
+        let results = fixture();
+
+        let by_duration: Vec<_> = results
+            .sorted_by_duration()
+            .iter()
+            .map(|journey| journey.segments()[0].train_number().as_str())
+            .collect();
+        assert_eq!(by_duration, vec!["8158", "9914"]);
+
+        let by_price: Vec<_> = results
+            .sorted_by_price()
+            .iter()
+            .map(|journey| journey.cheapest_price())
+            .collect();
+        assert_eq!(by_price, vec![Some(39.9), Some(19.9 + 29.9)]);
+    }
+
+    #[test]
+    fn journey_availability() {
+        let results = fixture();
+        let journeys = results.solutions()[0].journeys();
+
+        assert!(journeys.iter().all(Journey::has_availability));
+        let before = DateTime::from_timestamp(1721026800, 0).unwrap();
+        assert!(journeys.iter().any(|journey| journey.is_bookable(before)));
+        let after = DateTime::from_timestamp(1721113200, 0).unwrap();
+        assert!(!journeys.iter().any(|journey| journey.is_bookable(after)));
+        assert_eq!(journeys[1].segments()[1].fares().len(), 2);
+        assert!(!journeys[1].segments()[1].fares()[1].is_available());
+    }
+
+    #[test]
+    fn journey_total_price() {
+        let results = fixture();
+        let journeys = results.solutions()[0].journeys();
+        let mut request = JourneyRequest::default();
+
+        assert_eq!(journeys[0].total_price(&request), Some(39.9));
+
+        request
+            .set_adult_number(2)
+            .set_child_number(1)
+            .set_infant_number(1);
+        let total = journeys[1].total_price(&request).unwrap();
+        assert!((total - 3. * (19.9 + 29.9)).abs() < 1e-9);
+
+        let results: JourneyResults = serde_json::from_str(include_str!(
+            "../../tests/fixtures/journey_results_italobus.json"
+        ))
+        .unwrap();
+        assert_eq!(
+            results.solutions()[0].journeys()[0].total_price(&request),
+            None
+        );
+    }
+
+    #[test]
+    fn rome_day_window() {
+        let window = |date| {
+            let mut request = JourneyRequest::default();
+            request.set_day_rome(date).unwrap();
+            let body = request.to_request_json();
+            let search = &body["GetAvailableTrains"];
+            (
+                search["IntervalStartDateTime"].as_str().unwrap().to_owned(),
+                search["IntervalEndDateTime"].as_str().unwrap().to_owned(),
+            )
+        };
+
+        assert_eq!(
+            window(NaiveDate::from_ymd_opt(2024, 7, 15).unwrap()),
+            (
+                "/Date(1720994400000+0000)/".to_owned(),
+                "/Date(1721080800000+0000)/".to_owned()
+            )
+        );
+        let (start, end) = window(NaiveDate::from_ymd_opt(2024, 3, 31).unwrap());
+        assert_eq!(
+            extract_epoch_millis(&end).unwrap() - extract_epoch_millis(&start).unwrap(),
+            23 * 3600 * 1000
+        );
+    }
+
+    #[test]
+    fn upcoming_window() {
+        let station = |code: &str| Station::new(code.into(), Default::default(), code.into());
+        let now = DateTime::from_timestamp(1721026800, 0).unwrap();
+        let body =
+            JourneyRequest::next_hours(station("NAC"), station("MC_"), now, 2).to_request_json();
+        let search = &body["GetAvailableTrains"];
+
+        let start =
+            extract_epoch_millis(search["IntervalStartDateTime"].as_str().unwrap()).unwrap();
+        let end = extract_epoch_millis(search["IntervalEndDateTime"].as_str().unwrap()).unwrap();
+        assert_eq!(start, now.timestamp_millis());
+        assert_eq!(end - start, 2 * 3600 * 1000);
+        assert_eq!(search["OverrideIntervalTimeRestriction"], false);
+    }
+
+    #[test]
+    fn request_json() {
+        let request = JourneyRequest::one_way(
+            Station::new(
+                "NAC".into(),
+                "napoli-centrale".into(),
+                "Napoli Centrale".into(),
+            ),
+            Station::new(
+                "MC_".into(),
+                "milano-centrale".into(),
+                "Milano Centrale".into(),
+            ),
+            DateTime::from_timestamp(1721037600, 0).unwrap(),
+            Duration::hours(2),
+        );
+        let body = request.to_request_json();
+
+        assert!(body.get("Signature").is_none());
+        assert_eq!(body["SourceSystem"], 2);
+        let search = &body["GetAvailableTrains"];
+        assert_eq!(search["DepartureStation"], "NAC");
+        assert_eq!(search["ArrivalStation"], "MC_");
+        assert_eq!(
+            search["IntervalStartDateTime"],
+            "/Date(1721037600000+0000)/"
+        );
+        assert_eq!(search["IntervalEndDateTime"], "/Date(1721044800000+0000)/");
+        assert_eq!(search["AdultNumber"], 1);
+        assert!(search.get("RoundTripIntervalStartDateTime").is_none());
+    }
+
+    #[test]
+    fn seat_preferences_in_request() {
+        let mut request = JourneyRequest::default();
+        assert!(request.to_request_json()["GetAvailableTrains"]
+            .get("SeatPreferences")
+            .is_none());
+
+        let mut preferences = SeatPreferences::default();
+        preferences
+            .set_position(Some(SeatPosition::Window))
+            .set_quiet_area(true);
+        request.set_seat_preferences(Some(preferences));
+        assert_eq!(
+            request.to_request_json()["GetAvailableTrains"]["SeatPreferences"],
+            serde_json::json!({ "Position": "Window", "QuietArea": true })
+        );
+    }
+
+    #[test]
+    fn passenger_mix() {
+        let results = fixture();
+        let journey = &results.solutions()[0].journeys()[1];
+        let mut request = JourneyRequest::default();
+        request
+            .set_adult_number(2)
+            .set_child_number(1)
+            .set_infant_number(2);
+        assert!(request.validate().is_ok());
+        assert_eq!(request.seats_required(), 3);
+        assert!(journey.has_seats_for(&request));
+
+        request.set_adult_number(3);
+        assert!(!journey.has_seats_for(&request));
+
+        request.set_adult_number(0).set_senior_number(0);
+        assert!(request.validate().is_err());
+
+        request.set_senior_number(1);
+        assert!(request.validate().is_ok());
+
+        request
+            .set_senior_number(0)
+            .set_child_number(0)
+            .set_infant_number(0);
+        assert!(request.validate().is_err());
+    }
+
+    #[test]
+    fn cheapest_fare_of_the_search() {
+        assert_eq!(fixture().min_fare(), Some(39.9));
+
+        // The 19.9 leg alone is not a bookable journey
+        let results: JourneyResults = serde_json::from_str(
+            &include_str!("../../tests/fixtures/journey_results.json").replace("39.9", "99.9"),
+        )
+        .unwrap();
+        assert!((results.min_fare().unwrap() - 49.8).abs() < 1e-9);
+
+        let empty: JourneyResults = serde_json::from_str(r#"{"JourneyDateMarkets": []}"#).unwrap();
+        assert!(empty.min_fare().is_none());
+    }
+
+    #[test]
+    fn fare_rules() {
+        let rules = |json: &str| serde_json::from_str::<Fare>(json).unwrap().rules();
+
+        assert_eq!(
+            rules(r#"{"ProductClass": "Flex"}"#),
+            Some(FareRules {
+                refundable: true,
+                changeable: true
+            })
+        );
+        assert_eq!(
+            rules(r#"{"ProductClass": "Smart Economy"}"#),
+            Some(FareRules {
+                refundable: false,
+                changeable: true
+            })
+        );
+        assert_eq!(
+            rules(r#"{"ProductClass": "Low Cost"}"#),
+            Some(FareRules {
+                refundable: false,
+                changeable: false
+            })
+        );
+        assert_eq!(rules(r#"{"ProductClass": "Promo"}"#), None);
+        assert_eq!(
+            rules(r#"{"ProductClass": "Promo", "Refundable": false, "Changeable": true}"#),
+            Some(FareRules {
+                refundable: false,
+                changeable: true
+            })
+        );
+    }
+
+    #[test]
+    fn fare_amount_formats() {
+        for (amount, expected) in [
+            ("49.90", 49.9),
+            (r#""49,90""#, 49.9),
+            (r#""49.90""#, 49.9),
+            (r#""1.049,90""#, 1049.9),
+        ] {
+            let fare: Fare = serde_json::from_str(&format!(
+                r#"{{"ClassOfService": "Smart", "ProductClass": "Economy", "Amount": {amount}, "AvailableCount": 1}}"#
+            ))
+            .unwrap();
+            assert_eq!(fare.amount(), &expected, "{amount}");
+        }
+
+        assert!(serde_json::from_str::<Fare>(r#"{"Amount": "gratis"}"#).is_err());
+    }
+
+    #[test]
+    fn overnight_journey() {
+        // Departure 23:30 in Rome, arrival 01:10 on the following day
+        let journey: Journey = serde_json::from_str(
+            r#"{"Segments": [{
+                "STD": "/Date(1721079000000+0000)/",
+                "STA": "/Date(1721085000000+0000)/",
+                "TrainNumber": "9999",
+                "NoStopTrain": true,
+                "Legs": []
+            }]}"#,
+        )
+        .unwrap();
+        assert!(journey.is_overnight());
+        assert_eq!(journey.total_duration().unwrap(), Duration::minutes(100));
+
+        let results = fixture();
+        let daytime = &results.solutions()[0].journeys()[1];
+        assert!(!daytime.is_overnight());
+        assert_eq!(daytime.total_duration().unwrap(), Duration::hours(5));
+    }
+
+    #[test]
+    fn italobus_segment() {
+        let results: JourneyResults = serde_json::from_str(include_str!(
+            "../../tests/fixtures/journey_results_italobus.json"
+        ))
+        .unwrap();
+        let segments = results.solutions()[0].journeys()[0].segments();
+
+        assert_eq!(segments[0].mode(), SegmentMode::Train);
+        assert_eq!(segments[1].mode(), SegmentMode::Bus);
+        assert!(segments[1].train_number().is_empty());
+
+        assert!(segments[0].is_high_speed());
+        assert_eq!(segments[1].brand(), Brand::Italobus);
+        assert!(!segments[1].is_high_speed());
+    }
+
+    #[test]
+    fn segment_brand() {
+        let segment = |number: &str, code: &str| -> JourneySegment {
+            serde_json::from_value(serde_json::json!({
+                "STD": "/Date(1700000000000+0100)/",
+                "STA": "/Date(1700003600000+0100)/",
+                "TrainNumber": number,
+                "TrainType": code,
+                "Legs": []
+            }))
+            .unwrap()
+        };
+
+        assert_eq!(segment("9914", "").brand(), Brand::Italo);
+        assert_eq!(segment("3456", "").brand(), Brand::Other);
+        assert_eq!(segment("3456", "EVO").brand(), Brand::Italo);
+        assert_eq!(segment("IT-X", "").brand(), Brand::Italo);
+        assert!(!segment("3456", "").is_high_speed());
     }
 }
@@ -1,157 +1,1007 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anyhow::{Context, Ok};
+use futures::{future, stream, Stream, StreamExt};
 
+#[cfg(feature = "binary-cache")]
+pub use binary_cache::{from_cache_bytes, to_cache_bytes};
+pub use builder::ItaloApiBuilder;
+use chrono::Utc;
+pub use chrono_tz::Tz;
+pub use client::ItaloClient;
+pub use clock::{Clock, FixedClock, SystemClock};
+pub use control::StreamControl;
+pub use endpoint::EndpointConfig;
+pub use error::{ItaloError, ParseWarning};
+pub use health::{EndpointHealth, HealthStatus};
+pub use history::{DelayRecord, DelayStats, HistoryStore};
 use journey::InternalJourneyRequest;
 pub use journey::{
-    Journey, JourneyRequest, JourneyResults, JourneySegment, JourneysSolution, Stop,
+    Brand, Fare, FareRules, Journey, JourneyLookup, JourneyOption, JourneyRequest, JourneyResults,
+    JourneySegment, JourneysSolution, SeatPosition, SeatPreferences, SegmentMode, Stop,
+    Sustainability, TransferInfo, ValueWeights,
 };
 use login::{LoginRequestBody, LoginResponse};
-use reqwest::Client;
-pub use station::{Station, StationRealtime, StationTrainRealtime};
-use station::{StationCode, StationLabel};
-pub use train::{Disruption, TrainRealtime, TrainSchedule, TrainStation};
-
-static LOGIN_ENDPOINT: &str = "https://big.ntvspa.it/BIG/v7/Rest/SessionManager.svc/Login";
-static STATION_LIST_ENDPOINT: &str = "https://italoinviaggio.italotreno.it/it/stazione";
-static STATION_REALTIME_ENDPOINT: &str =
-    "https://italoinviaggio.italotreno.it/api/RicercaStazioneService?&CodiceStazione=";
-static TRAIN_REALTIME_ENDPOINT: &str =
-    "https://italoinviaggio.italotreno.it/api/RicercaTrenoService?&TrainNumber=";
+use reqwest::{
+    header::{CONTENT_TYPE, RETRY_AFTER},
+    Client, Response, StatusCode,
+};
+pub use schema::{EndpointReport, SchemaReport};
+use serde::Serialize;
+pub use station::{
+    BoardDiff, BoardKind, DelayClass, DelayThresholds, Station, StationListMarkers, StationLookup,
+    StationRealtime, StationTrainRealtime, TimelineEntry, TrainBoardStatus,
+};
+pub use time::ItaloDateTime;
+use tokio::sync::{watch, OnceCell};
+pub use train::{
+    Disruption, ServiceType, StopProgress, StopState, TrainEvent, TrainNumber, TrainRealtime,
+    TrainSchedule, TrainStation, TrainSummary,
+};
 
-static SEARCH_SOLUTIONS: &str =
-    "https://big.ntvspa.it/BIG/v7/Rest/BookingManager.svc/GetAvailableTrains";
+static SOURCE_SYSTEM: u8 = 2;
+static REACHABLE_WINDOW_DAYS: i64 = 7;
+static NEXT_TRAIN_WINDOW_HOURS: i64 = 4;
+static REFRESH_WINDOW_MINUTES: i64 = 1;
+static STATION_LIST_TIMEOUT_SECONDS: u64 = 5;
+static ERROR_BODY_SNIPPET_CHARS: usize = 200;
+static SCHEMA_CHECK_STATIONS: [(&str, &str, &str); 2] = [
+    ("NAC", "napoli-centrale", "Napoli Centrale"),
+    ("MC_", "milano-centrale", "Milano Centrale"),
+];
 
+#[cfg(feature = "binary-cache")]
+mod binary_cache;
+mod builder;
+mod client;
+mod clock;
+mod control;
+mod de;
+mod endpoint;
+mod error;
+#[cfg(test)]
+mod fixture;
+mod health;
+mod history;
+#[cfg(feature = "ical")]
+mod ical;
 mod journey;
 mod login;
+#[cfg(feature = "record")]
+mod record;
+mod schema;
 mod station;
+mod time;
 mod train;
 
 /// Use this struct to access italotreno API.
 ///
-/// Use [`Self::default()`] to instantiate the interface.
+/// Use [`Self::default()`] to instantiate the interface or [`Self::builder()`] to customize it.
 ///
-#[derive(Default)]
 pub struct ItaloApi {
-    signature: Option<LoginResponse>,
+    signature: OnceCell<LoginResponse>,
     client: Client,
+    display_timezone: Tz,
+    endpoints: EndpointConfig,
+    deadline: Option<Duration>,
+    max_concurrency: usize,
+    max_response_bytes: usize,
+    auto_login: bool,
+    station_list_markers: StationListMarkers,
+    clock: Arc<dyn Clock>,
+    reachable_cache: HashMap<String, Vec<Station>>,
+    direct_cache: HashMap<(String, String), bool>,
+    station_cache: Option<(Instant, Vec<Station>)>,
+    station_cache_ttl: Duration,
+    #[cfg(feature = "record")]
+    record_to: Option<std::path::PathBuf>,
+}
+
+struct RawResponse {
+    status: StatusCode,
+    content_type: Option<String>,
+    retry_after: Option<Duration>,
+    body: String,
+}
+
+impl RawResponse {
+    /// Turn 429 and 5xx answers into [`ItaloError::ServerError`]
+    fn error_for_status(self) -> anyhow::Result<Self> {
+        if self.status != StatusCode::TOO_MANY_REQUESTS && !self.status.is_server_error() {
+            return Ok(self);
+        }
+
+        let body = self.body.trim();
+        let body_snippet = match body.char_indices().nth(ERROR_BODY_SNIPPET_CHARS) {
+            Some((end, _)) => format!("{}...", &body[..end]),
+            None => body.to_owned(),
+        };
+        Err(ItaloError::ServerError {
+            status: self.status.as_u16(),
+            retry_after: self.retry_after,
+            body_snippet,
+        }
+        .into())
+    }
+}
+
+impl Default for ItaloApi {
+    fn default() -> Self {
+        Self::builder()
+            .build()
+            .expect("Failed to build the http client")
+    }
 }
 
 impl ItaloApi {
+    /// Customize the interface before instantiating it
+    pub fn builder() -> ItaloApiBuilder {
+        ItaloApiBuilder::default()
+    }
+
+    /// Timezone configured for displaying times.
+    ///
+    /// Pass it to the `*_in()` accessors, times are always parsed as UTC.
+    pub fn display_timezone(&self) -> Tz {
+        self.display_timezone
+    }
+
+    /// Hosts contacted by the interface
+    pub fn endpoints(&self) -> &EndpointConfig {
+        &self.endpoints
+    }
+
+    /// Current time according to [`ItaloApiBuilder::clock()`]
+    pub fn now(&self) -> chrono::DateTime<Utc> {
+        self.clock.now()
+    }
+
+    async fn get(&self, url: &str) -> anyhow::Result<RawResponse> {
+        let res = self.client.get(url).send().await?;
+        tracing::debug!("GET resolved to {}", res.url());
+
+        self.read(url, None, res).await?.error_for_status()
+    }
+
+    async fn post(&self, url: &str, body: &impl Serialize) -> anyhow::Result<RawResponse> {
+        let request = serde_json::to_string(body)?;
+        let res = self
+            .client
+            .post(url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(request.clone())
+            .send()
+            .await?;
+
+        self.read(url, Some(&request), res)
+            .await?
+            .error_for_status()
+    }
+
+    async fn read(
+        &self,
+        url: &str,
+        request: Option<&str>,
+        mut res: Response,
+    ) -> anyhow::Result<RawResponse> {
+        let status = res.status();
+        let content_type = res
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|elem| elem.to_str().ok())
+            .map(str::to_owned);
+        let retry_after = res
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|elem| elem.to_str().ok())
+            .and_then(|elem| self.parse_retry_after(elem));
+        let too_large = ItaloError::ResponseTooLarge(self.max_response_bytes);
+        if res
+            .content_length()
+            .is_some_and(|len| len > self.max_response_bytes as u64)
+        {
+            return Err(too_large.into());
+        }
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = res.chunk().await? {
+            if bytes.len() + chunk.len() > self.max_response_bytes {
+                return Err(too_large.into());
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        let body = String::from_utf8_lossy(&bytes).into_owned();
+        self.record(url, request, &body).await;
+
+        Ok(RawResponse {
+            status,
+            content_type,
+            retry_after,
+            body,
+        })
+    }
+
+    /// `Retry-After` holds either a number of seconds or an http date
+    fn parse_retry_after(&self, val: &str) -> Option<Duration> {
+        let val = val.trim();
+        if let Result::Ok(seconds) = val.parse() {
+            return Some(Duration::from_secs(seconds));
+        }
+        let date = chrono::DateTime::parse_from_rfc2822(val).ok()?;
+        Some(
+            (date.with_timezone(&Utc) - self.now())
+                .to_std()
+                .unwrap_or_default(),
+        )
+    }
+
+    #[cfg(feature = "record")]
+    async fn record(&self, url: &str, request: Option<&str>, response: &str) {
+        if let Some(dir) = &self.record_to {
+            record::record(dir, url, request, response).await
+        }
+    }
+
+    #[cfg(not(feature = "record"))]
+    async fn record(&self, _url: &str, _request: Option<&str>, _response: &str) {}
+
+    /// Run `fut` unless the deadline started at `start` expires first
+    async fn within_deadline<T>(
+        &self,
+        start: tokio::time::Instant,
+        fut: impl Future<Output = anyhow::Result<T>>,
+    ) -> anyhow::Result<T> {
+        match self.deadline {
+            Some(deadline) => tokio::time::timeout_at(start + deadline, fut)
+                .await
+                .unwrap_or_else(|_| Err(ItaloError::DeadlineExceeded.into())),
+            None => fut.await,
+        }
+    }
+
+    #[cfg(test)]
     fn is_initialized(&self) -> bool {
-        self.signature.is_some()
+        self.signature.initialized()
     }
 
-    async fn init(&mut self) -> anyhow::Result<()> {
-        self.signature = Some(
-            self.client
-                .post(LOGIN_ENDPOINT)
-                .json(&LoginRequestBody::default())
-                .send()
-                .await?
-                .json()
-                .await?,
+    /// Session signature, logging in on first use unless [`ItaloApiBuilder::auto_login()`] is disabled.
+    async fn signature(&self) -> anyhow::Result<&str> {
+        match self.signature.get() {
+            Some(signature) => Ok(signature),
+            None if self.auto_login => self.login_signature().await,
+            None => Err(ItaloError::NotAuthenticated.into()),
+        }
+    }
+
+    /// Concurrent callers share a single login
+    async fn login_signature(&self) -> anyhow::Result<&str> {
+        Ok(self
+            .signature
+            .get_or_try_init(|| async {
+                let res = self
+                    .post(&self.endpoints.login(), &LoginRequestBody::default())
+                    .await?;
+                login::parse_login(res.status, &res.body)
+            })
+            .await?)
+    }
+
+    /// Open the session used by the journey search, the other calls do not need it.
+    ///
+    /// Optional unless [`ItaloApiBuilder::auto_login()`] is disabled, refusals are reported as [`ItaloError::Auth`],
+    /// 429 and 5xx answers as [`ItaloError::ServerError`].
+    /// Once logged in further calls do nothing
+    pub async fn login(&self) -> anyhow::Result<()> {
+        self.login_signature().await?;
+        Ok(())
+    }
+
+    /// Prime DNS cache and connection pool towards italotreno hosts.
+    ///
+    /// Calling it is optional and best-effort: failures are logged and never returned.
+    pub async fn warmup(&self) -> anyhow::Result<()> {
+        let hosts = self.endpoints.hosts();
+        let (big, realtime) = tokio::join!(
+            self.client.head(hosts[0]).send(),
+            self.client.head(hosts[1]).send()
         );
+
+        [big, realtime]
+            .into_iter()
+            .zip(hosts)
+            .filter_map(|(res, host)| res.err().map(|err| (host, err)))
+            .for_each(|(host, err)| tracing::warn!("Warmup of {host} failed: {err}"));
+
         Ok(())
     }
 
+    /// Ping login and realtime hosts reporting their reachability and latency
+    pub async fn health_check(&self) -> HealthStatus {
+        let ping = |host| async move {
+            let start = Instant::now();
+            let res = self.client.head(host).send().await;
+            EndpointHealth::new(res.ok().map(|_| start.elapsed()))
+        };
+
+        let (login, realtime) = tokio::join!(
+            ping(self.endpoints.big_host()),
+            ping(self.endpoints.realtime_host())
+        );
+        HealthStatus::new(login, realtime)
+    }
+
     /// Retrieves stations recognized by the italotreno information system.
     ///
     /// The struct contains internal Ids used by [`Self::station_realtime()`]
     pub async fn station_list(&self) -> anyhow::Result<Vec<Station>> {
-        let res = self
-            .client
-            .get(STATION_LIST_ENDPOINT)
-            .send()
+        let page = self.get(&self.endpoints.station_list()).await?.body;
+        station::parse_station_list(&page, &self.station_list_markers)
+    }
+
+    /// Same as [`Self::station_list()`] but list entries that fail to parse are skipped and reported as [`ParseWarning`]
+    pub async fn station_list_lenient(&self) -> anyhow::Result<(Vec<Station>, Vec<ParseWarning>)> {
+        let page = self.get(&self.endpoints.station_list()).await?.body;
+        station::parse_station_list_lenient(&page, &self.station_list_markers)
+    }
+
+    /// Same as [`Self::station_list()`] but the list is scraped again only once the cache TTL expires
+    pub async fn cached_station_list(&mut self) -> anyhow::Result<Vec<Station>> {
+        match &self.station_cache {
+            Some((fetched, stations)) if fetched.elapsed() < self.station_cache_ttl => {
+                Ok(stations.clone())
+            }
+            _ => {
+                self.refresh_stations().await?;
+                Ok(self
+                    .station_cache
+                    .as_ref()
+                    .map(|(_, stations)| stations.clone())
+                    .unwrap_or_default())
+            }
+        }
+    }
+
+    /// Scrape the station list again and replace the cache used by [`Self::cached_station_list()`], regardless of its TTL
+    ///
+    /// Returns the number of stations fetched. On failure the previous cache is left untouched
+    pub async fn refresh_stations(&mut self) -> anyhow::Result<usize> {
+        let stations = self.station_list().await?;
+        let count = stations.len();
+        self.station_cache = Some((Instant::now(), stations));
+        Ok(count)
+    }
+
+    /// Best available station list: scrape it again with a short timeout, falling back to the last cached list
+    /// (logging a warning) when the scrape fails.
+    ///
+    /// A successful scrape refreshes the cache used by [`Self::cached_station_list()`]. Fails only when the
+    /// scrape fails and no list was cached yet
+    pub async fn station_list_or_cached(&mut self) -> anyhow::Result<Vec<Station>> {
+        let timeout = Duration::from_secs(STATION_LIST_TIMEOUT_SECONDS);
+        let res = tokio::time::timeout(timeout, self.station_list())
+            .await
+            .unwrap_or_else(|_| Err(ItaloError::DeadlineExceeded.into()));
+
+        match (res, &self.station_cache) {
+            (Result::Ok(stations), _) => {
+                self.station_cache = Some((Instant::now(), stations.clone()));
+                Ok(stations)
+            }
+            (Err(err), Some((_, stations))) => {
+                tracing::warn!("Station list scrape failed, using the cached list: {err:#}");
+                Ok(stations.clone())
+            }
+            (Err(err), None) => Err(err.context("No cached station list to fall back to")),
+        }
+    }
+
+    /// Resolve a station from its italoinviaggio URL slug (e.g. `milano-centrale`) using [`Self::cached_station_list()`]
+    pub async fn station_by_url_coding(&mut self, slug: &str) -> anyhow::Result<Option<Station>> {
+        let slug = slug.trim().trim_matches('/');
+        Ok(self
+            .cached_station_list()
             .await?
-            .text()
-            .await?;
+            .into_iter()
+            .find(|station| station.url_coding().eq_ignore_ascii_case(slug)))
+    }
 
-        let raw_lists = res
-            .split_once("ItaloInViaggio.Resources.stationList = ")
-            .context("stationList not found")?
-            .1
-            .split_once("ItaloInViaggio.Resources.stationCoding = ")
-            .context("stationCoding not found")?;
-
-        let label_list: Vec<StationLabel> =
-            serde_json::from_str(raw_lists.0.trim_end().trim_end_matches(';'))?;
-
-        let code_list: Vec<StationCode> = serde_json::from_str(
-            raw_lists
-                .1
-                .split_once("ItaloInViaggio.Resources.localizzation")
-                .context("localization not found")?
-                .0
-                .trim_end()
-                .trim_end_matches(';'),
-        )?;
-
-        let label_map = label_list
+    /// Map Italo station IDs to their human friendly names using [`Self::cached_station_list()`].
+    ///
+    /// Codes missing from the list are left out of the map
+    pub async fn resolve_station_names(
+        &mut self,
+        codes: &[&str],
+    ) -> anyhow::Result<HashMap<String, String>> {
+        let codes: HashSet<_> = codes.iter().map(|code| code.trim()).collect();
+        Ok(self
+            .cached_station_list()
+            .await?
             .iter()
-            .map(|elem| (elem.value(), elem.label()))
-            .collect::<HashMap<_, _>>();
+            .filter(|station| codes.contains(station.code().as_str()))
+            .map(|station| (station.code().clone(), station.name().clone()))
+            .collect())
+    }
 
-        Ok(code_list
-            .iter()
-            .map(|elem| {
-                Station::new(
-                    elem.code().to_owned(),
-                    elem.url_coding().to_owned(),
-                    label_map
-                        .get(elem.code())
-                        .unwrap_or(&&"".to_string())
-                        .to_string(),
+    /// Search a station by its human friendly name among the ones returned by [`Self::station_list()`]
+    pub async fn find_station(&self, name: &str) -> anyhow::Result<StationLookup> {
+        Ok(StationLookup::search(self.station_list().await?, name))
+    }
+
+    /// Stations matching `name` so the caller can pick one, e.g. both Roma stations for `Roma`.
+    ///
+    /// Empty when nothing matches, see [`Self::find_station()`]
+    pub async fn disambiguate(&self, name: &str) -> anyhow::Result<Vec<Station>> {
+        Ok(self.find_station(name).await?.into_candidates())
+    }
+
+    /// Find the closest station to the given coordinates among the ones returned by [`Self::station_list()`]
+    pub async fn nearest_station(&self, lat: f64, lon: f64) -> anyhow::Result<Station> {
+        self.station_list()
+            .await?
+            .into_iter()
+            .filter_map(|station| station.distance_km(lat, lon).map(|dist| (dist, station)))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, station)| station)
+            .context("No station with coordinates")
+    }
+
+    /// Reject station codes that would silently return an empty board: malformed ones and,
+    /// once [`Self::cached_station_list()`] has been filled, codes missing from the list
+    fn check_station_code(&self, code: &str) -> anyhow::Result<()> {
+        Station::validate_code(code)?;
+        match &self.station_cache {
+            Some((_, stations)) if !stations.iter().any(|station| station.code() == code) => {
+                Err(anyhow::anyhow!("Unknown station code {code:?}"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Retrieve the departure and arrival boards for a station
+    ///
+    /// The station code is checked first, see [`Station::validate_code()`]
+    pub async fn station_realtime(&self, station: Station) -> anyhow::Result<StationRealtime> {
+        self.check_station_code(station.code())?;
+        Ok(serde_json::from_str(
+            &self
+                .get(&self.endpoints.station_realtime(station.code()))
+                .await?
+                .body,
+        )?)
+    }
+
+    /// Same as [`Self::station_realtime()`] but board entries that fail to parse are skipped and reported as [`ParseWarning`]
+    pub async fn station_realtime_lenient(
+        &self,
+        station: Station,
+    ) -> anyhow::Result<(StationRealtime, Vec<ParseWarning>)> {
+        self.check_station_code(station.code())?;
+        station::parse_station_realtime_lenient(
+            &self
+                .get(&self.endpoints.station_realtime(station.code()))
+                .await?
+                .body,
+        )
+    }
+
+    /// Retrieve concurrently the boards of several stations using [`Self::station_realtime()`]
+    ///
+    /// Results are returned in completion order, at most [`ItaloApiBuilder::max_concurrency()`] requests run at once.
+    /// Boards not retrieved within the configured deadline are reported as [`ItaloError::DeadlineExceeded`]
+    pub async fn station_realtime_many(
+        &self,
+        stations: &[Station],
+    ) -> Vec<(Station, anyhow::Result<StationRealtime>)> {
+        let start = tokio::time::Instant::now();
+        stream::iter(stations.iter().cloned())
+            .map(|station| async move {
+                (
+                    station.clone(),
+                    self.within_deadline(start, self.station_realtime(station))
+                        .await,
                 )
             })
-            .filter(|elem| !elem.name().is_empty())
-            .collect())
+            .buffer_unordered(self.max_concurrency)
+            .collect()
+            .await
     }
 
-    /// Retrieve the departure and arrival boards for a station using [`Self::station_realtime()`]
-    pub async fn station_realtime(&self, station: Station) -> anyhow::Result<StationRealtime> {
+    /// Retrieve realtime data on a moving train
+    ///
+    /// The train code is normalized with [`TrainNumber::parse()`], so prefixes like `"ITA 8158"` are accepted.
+    /// Unknown trains are reported as [`ItaloError::TrainNotFound`]
+    pub async fn train_realtime(&self, train_code: &str) -> anyhow::Result<TrainRealtime> {
+        let train_number = TrainNumber::parse(train_code)?;
+        let res = self
+            .get(&self.endpoints.train_realtime(train_number.as_str()))
+            .await?;
+
+        train::parse_train_realtime(
+            train_number.as_str(),
+            res.content_type.as_deref(),
+            &res.body,
+        )
+    }
+
+    /// Poll [`Self::train_realtime()`] every `interval`, yielding every snapshot or failed poll.
+    ///
+    /// The stream ends right after the snapshot showing the train at its terminus,
+    /// see [`TrainRealtime::is_completed()`]
+    pub fn track_until_arrival(
+        &self,
+        train_code: &str,
+        interval: Duration,
+    ) -> impl Stream<Item = anyhow::Result<TrainRealtime>> + '_ {
+        self.poll_until_arrival(train_code, interval, watch::channel(false).1)
+    }
+
+    /// Same as [`Self::track_until_arrival()`], polling can be paused and resumed with the returned [`StreamControl`]
+    pub fn track_until_arrival_controlled(
+        &self,
+        train_code: &str,
+        interval: Duration,
+    ) -> (
+        StreamControl,
+        impl Stream<Item = anyhow::Result<TrainRealtime>> + '_,
+    ) {
+        let (control, paused) = StreamControl::new();
+        (
+            control,
+            self.poll_until_arrival(train_code, interval, paused),
+        )
+    }
+
+    fn poll_until_arrival(
+        &self,
+        train_code: &str,
+        interval: Duration,
+        paused: watch::Receiver<bool>,
+    ) -> impl Stream<Item = anyhow::Result<TrainRealtime>> + '_ {
+        let train_code = train_code.to_owned();
+        stream::unfold(
+            (true, false, paused),
+            move |(first, completed, mut paused)| {
+                let train_code = train_code.clone();
+                async move {
+                    if completed {
+                        return None;
+                    }
+                    if !first {
+                        tokio::time::sleep(interval).await;
+                    }
+                    // A dropped control can not pause the stream anymore
+                    let _ = paused.wait_for(|paused| !paused).await;
+
+                    let res = self.train_realtime(&train_code).await;
+                    let completed = res.as_ref().is_ok_and(TrainRealtime::is_completed);
+                    Some((res, (false, completed, paused)))
+                }
+            },
+        )
+    }
+
+    /// Poll [`Self::train_realtime()`] every `interval` and yield only the changes between snapshots.
+    ///
+    /// Failed polls are logged and retried at the next tick. The stream ends once the train has
+    /// completed its trip, see [`Self::track_until_arrival()`]
+    pub fn train_events(
+        &self,
+        train_code: &str,
+        interval: Duration,
+    ) -> impl Stream<Item = TrainEvent> + '_ {
+        Self::events_of(train_code, self.track_until_arrival(train_code, interval))
+    }
+
+    /// Same as [`Self::train_events()`], polling can be paused and resumed with the returned [`StreamControl`]
+    pub fn train_events_controlled(
+        &self,
+        train_code: &str,
+        interval: Duration,
+    ) -> (StreamControl, impl Stream<Item = TrainEvent> + '_) {
+        let (control, snapshots) = self.track_until_arrival_controlled(train_code, interval);
+        (control, Self::events_of(train_code, snapshots))
+    }
+
+    fn events_of<'a>(
+        train_code: &str,
+        snapshots: impl Stream<Item = anyhow::Result<TrainRealtime>> + 'a,
+    ) -> impl Stream<Item = TrainEvent> + 'a {
+        let train = train_code.to_owned();
+        snapshots
+            .scan(None::<TrainRealtime>, move |previous, res| {
+                let events = match res {
+                    Result::Ok(current) => {
+                        let events = previous
+                            .as_ref()
+                            .map(|previous| current.events_since(previous))
+                            .unwrap_or_default();
+                        *previous = Some(current);
+                        events
+                    }
+                    Err(err) => {
+                        tracing::warn!("Polling train {train} failed: {err:#}");
+                        vec![]
+                    }
+                };
+                future::ready(Some(events))
+            })
+            .flat_map(stream::iter)
+    }
+
+    /// Poll [`Self::train_realtime()`] once and append the observed delay to `store`.
+    ///
+    /// Italo does not publish past performance: call it periodically, e.g. around the arrival time,
+    /// to build a local history and aggregate it with [`DelayStats::from_records()`]
+    pub async fn record_train(
+        &self,
+        train_code: &str,
+        store: &mut impl HistoryStore,
+    ) -> anyhow::Result<DelayRecord> {
+        let train = self.train_realtime(train_code).await?;
+        let record = DelayRecord::from_realtime(&train, self.now());
+        store.save(record.clone())?;
+        Ok(record)
+    }
+
+    /// Retrieve concurrently realtime data on several trains using [`Self::train_realtime()`]
+    ///
+    /// Results are returned in completion order, at most [`ItaloApiBuilder::max_concurrency()`] requests run at once.
+    /// Trains not retrieved within the configured deadline are reported as [`ItaloError::DeadlineExceeded`]
+    pub async fn train_realtime_many(
+        &self,
+        train_codes: &[&str],
+    ) -> Vec<(String, anyhow::Result<TrainRealtime>)> {
+        let start = tokio::time::Instant::now();
+        let train_codes: Vec<_> = train_codes.iter().map(ToString::to_string).collect();
+        stream::iter(train_codes)
+            .map(|train_code| async move {
+                let res = self
+                    .within_deadline(start, self.train_realtime(&train_code))
+                    .await;
+                (train_code, res)
+            })
+            .buffer_unordered(self.max_concurrency)
+            .collect()
+            .await
+    }
+
+    /// Resolve the Rete Ferroviaria Italiana number of a station board entry.
+    ///
+    /// Station boards only expose the Italo number, so the RFI one is read from [`Self::train_realtime()`]
+    pub async fn rfi_number(&self, train: &StationTrainRealtime) -> anyhow::Result<String> {
         Ok(self
-            .client
-            .get(STATION_REALTIME_ENDPOINT.to_string() + station.code())
-            .send()
+            .train_realtime(train.number())
             .await?
-            .json()
-            .await?)
+            .train_schedule()
+            .rfi_train_number()
+            .to_owned())
     }
 
-    /// Retrieve realtime data on a moving train
-    pub async fn train_realtime(&self, train_code: &str) -> anyhow::Result<TrainRealtime> {
+    async fn search(&self, journey: &JourneyRequest) -> anyhow::Result<String> {
+        let signature = self.signature().await?;
         Ok(self
-            .client
-            .get(TRAIN_REALTIME_ENDPOINT.to_string() + train_code)
-            .send()
+            .post(
+                &self.endpoints.search_solutions(),
+                &InternalJourneyRequest::new(signature, SOURCE_SYSTEM, journey),
+            )
             .await?
-            .json()
-            .await?)
+            .body)
     }
 
     /// Search journey solutions between stations
-    pub async fn find_journeys(
+    ///
+    /// The first call logs in, the session is then shared by every task using this instance.
+    /// The passenger mix is checked with [`JourneyRequest::validate()`] before anything is sent,
+    /// journeys departing before [`JourneyRequest::set_min_departure()`] are dropped.
+    pub async fn find_journeys(&self, journey: &JourneyRequest) -> anyhow::Result<JourneyResults> {
+        journey.validate()?;
+        let mut results: JourneyResults = serde_json::from_str(&self.search(journey).await?)?;
+        if let Some(min_departure) = journey.min_departure() {
+            results.retain_departing_after(min_departure);
+        }
+        Ok(results)
+    }
+
+    /// Same as [`Self::find_journeys()`] but journeys that fail to parse are skipped and reported as [`ParseWarning`]
+    pub async fn find_journeys_lenient(
+        &self,
+        journey: &JourneyRequest,
+    ) -> anyhow::Result<(JourneyResults, Vec<ParseWarning>)> {
+        journey.validate()?;
+        let (mut results, warnings) =
+            journey::parse_journey_results_lenient(&self.search(journey).await?)?;
+        if let Some(min_departure) = journey.min_departure() {
+            results.retain_departing_after(min_departure);
+        }
+        Ok((results, warnings))
+    }
+
+    /// Same as [`Self::find_journeys()`] with the stations searched by name, see [`Self::find_station()`].
+    ///
+    /// The search runs only when both names resolve to a single station, otherwise the candidates are returned
+    pub async fn find_journeys_by_name(
+        &self,
+        from: &str,
+        to: &str,
+        mut journey: JourneyRequest,
+    ) -> anyhow::Result<JourneyLookup> {
+        let stations = self.station_list().await?;
+        let departure = StationLookup::search(stations.clone(), from).into_candidates();
+        let arrival = StationLookup::search(stations, to).into_candidates();
+
+        match (departure.len(), arrival.len()) {
+            (0, _) => Ok(JourneyLookup::NotFound(from.to_owned())),
+            (_, 0) => Ok(JourneyLookup::NotFound(to.to_owned())),
+            (1, 1) => {
+                journey
+                    .set_departure_station(departure[0].clone())
+                    .set_arrival_station(arrival[0].clone());
+                Ok(JourneyLookup::Found(self.find_journeys(&journey).await?))
+            }
+            _ => Ok(JourneyLookup::Ambiguous { departure, arrival }),
+        }
+    }
+
+    /// Same as [`Self::find_journeys()`] leaving from any station of `city` (e.g. `Roma`), matched by name
+    /// among [`Self::cached_station_list()`].
+    ///
+    /// The searches run concurrently, their results are merged, listed once and sorted by departure time.
+    /// Failed searches from single stations are logged and left out, an error is returned only when all of them fail
+    pub async fn find_journeys_from_city(
         &mut self,
+        city: &str,
+        to: &Station,
         journey: &JourneyRequest,
     ) -> anyhow::Result<JourneyResults> {
-        match self.is_initialized() {
-            true => Ok(()),
-            false => self.init().await,
-        }?;
+        let origins: Vec<_> = StationLookup::search(self.cached_station_list().await?, city)
+            .into_candidates()
+            .into_iter()
+            .filter(|station| station.code() != to.code())
+            .collect();
+        if origins.is_empty() {
+            return Err(anyhow::anyhow!("No station matches {city}"));
+        }
+
+        let this = &*self;
+        let searches: Vec<_> = stream::iter(origins)
+            .map(|origin| {
+                let mut request = journey.clone();
+                request
+                    .set_departure_station(origin.clone())
+                    .set_arrival_station(to.clone());
+                async move { (origin, this.find_journeys(&request).await) }
+            })
+            .buffered(self.max_concurrency)
+            .collect()
+            .await;
+
+        let mut results = Vec::new();
+        let mut last_err = None;
+        for (origin, search) in searches {
+            match search {
+                Result::Ok(elem) => results.push(elem),
+                Err(err) => {
+                    tracing::warn!("Search from {} failed: {err:#}", origin.code());
+                    last_err = Some(err);
+                }
+            }
+        }
+        match (results.is_empty(), last_err) {
+            (true, Some(err)) => Err(err.context(format!("Every search from {city} failed"))),
+            _ => Ok(JourneyResults::merge(results)),
+        }
+    }
+
+    /// Earliest bookable journey from `from` to `to` departing in the next few hours, `None` when there is none
+    pub async fn next_train(
+        &self,
+        from: &Station,
+        to: &Station,
+    ) -> anyhow::Result<Option<Journey>> {
+        let now = self.now();
+        let request = JourneyRequest::one_way(
+            from.clone(),
+            to.clone(),
+            now,
+            chrono::Duration::hours(NEXT_TRAIN_WINDOW_HOURS),
+        );
 
         Ok(self
-            .client
-            .post(SEARCH_SOLUTIONS)
-            .json(&InternalJourneyRequest::new(
-                self.signature.as_deref().unwrap(),
-                2,
-                journey,
-            ))
-            .send()
+            .find_journeys(&request)
             .await?
-            .json()
-            .await?)
+            .into_journeys()
+            .filter(|journey| journey.is_bookable(now))
+            .filter_map(|journey| Some((journey.departure_time().ok()?, journey)))
+            .min_by_key(|(departure, _)| *departure)
+            .map(|(_, journey)| journey))
+    }
+
+    /// Search `journey` again to get its current fares and availability, `None` when it is no longer offered.
+    ///
+    /// The search covers the same stations around the original departure time with the passengers, currency
+    /// and seat preferences of `search`, the request that found `journey`. The journey is matched
+    /// on the train numbers and departure times of its segments
+    pub async fn refresh_journey(
+        &self,
+        journey: &Journey,
+        search: &JourneyRequest,
+    ) -> anyhow::Result<Option<Journey>> {
+        let (from, to) = journey.endpoints().context("Journey without stops")?;
+        let station =
+            |code: &str| Station::new(code.to_owned(), Default::default(), Default::default());
+        let departure = journey.departure_time()?;
+        let window = chrono::Duration::minutes(REFRESH_WINDOW_MINUTES);
+
+        let mut request = search.clone();
+        request
+            .set_round_trip((false, None, None))?
+            .set_departure_station(station(from))
+            .set_arrival_station(station(to))
+            .set_search_window(departure - window, departure + window)
+            .set_min_departure(None);
+
+        let identity = journey.identity();
+        Ok(self
+            .find_journeys(&request)
+            .await?
+            .into_journeys()
+            .find(|elem| elem.identity() == identity))
+    }
+
+    /// Whether a non-stop train, flagged `NoStopTrain` by the search, runs from `from` to `to`.
+    ///
+    /// Like [`Self::reachable_stations()`] it searches the next week, results are cached per station pair.
+    pub async fn has_direct_service(
+        &mut self,
+        from: &Station,
+        to: &Station,
+    ) -> anyhow::Result<bool> {
+        let key = (from.code().to_owned(), to.code().to_owned());
+        if let Some(direct) = self.direct_cache.get(&key) {
+            return Ok(*direct);
+        }
+
+        let start = self.now() + chrono::Duration::days(1);
+        let mut request = JourneyRequest::default();
+        request
+            .set_departure_station(from.clone())
+            .set_arrival_station(to.clone())
+            .set_search_window(start, start + chrono::Duration::days(REACHABLE_WINDOW_DAYS));
+
+        let direct = self
+            .find_journeys(&request)
+            .await?
+            .journeys()
+            .flat_map(|journey| journey.segments())
+            .any(|segment| *segment.no_stop_train());
+        self.direct_cache.insert(key, direct);
+        Ok(direct)
+    }
+
+    /// Stations reachable from `from`, possibly with changes.
+    ///
+    /// This is a heuristic, not an official endpoint: a search with an empty arrival station is issued over the
+    /// next week and every stop served by the returned journeys is matched against [`Self::cached_station_list()`].
+    /// Results are cached per station.
+    ///
+    /// GetAvailableTrains is not known to accept a search without arrival station: when italo rejects it
+    /// the search error is returned as is, with a context saying so
+    pub async fn reachable_stations(&mut self, from: &Station) -> anyhow::Result<Vec<Station>> {
+        if let Some(cached) = self.reachable_cache.get(from.code()) {
+            return Ok(cached.clone());
+        }
+
+        let start = self.now() + chrono::Duration::days(1);
+        let mut request = JourneyRequest::default();
+        request
+            .set_departure_station(from.clone())
+            .set_search_window(start, start + chrono::Duration::days(REACHABLE_WINDOW_DAYS));
+
+        let results = self
+            .find_journeys(&request)
+            .await
+            .context("Search without arrival station refused, reachable stations unavailable")?;
+        let codes: HashSet<&str> = results
+            .journeys()
+            .flat_map(|journey| journey.segments())
+            .flat_map(|segment| segment.stops())
+            .map(|stop| stop.arrival_station().as_str())
+            .filter(|code| *code != from.code())
+            .collect();
+
+        let reachable: Vec<Station> = self
+            .cached_station_list()
+            .await?
+            .into_iter()
+            .filter(|station| codes.contains(station.code().as_str()))
+            .collect();
+
+        self.reachable_cache
+            .insert(from.code().to_owned(), reachable.clone());
+        Ok(reachable)
+    }
+
+    /// Compare the responses of the realtime and search endpoints against the crate models.
+    ///
+    /// Aimed at maintainers and power users: unknown and missing fields are reported per endpoint instead of failing.
+    pub async fn check_schema(&self) -> SchemaReport {
+        let [from, to] = SCHEMA_CHECK_STATIONS.map(|(code, url_coding, name)| {
+            Station::new(code.to_owned(), url_coding.to_owned(), name.to_owned())
+        });
+        let mut report = SchemaReport::default();
+
+        let board = self.get(&self.endpoints.station_realtime(to.code())).await;
+        let train_code = board
+            .as_ref()
+            .ok()
+            .and_then(|res| serde_json::from_str::<StationRealtime>(&res.body).ok())
+            .and_then(|board| {
+                board
+                    .departure_board()
+                    .first()
+                    .map(|train| train.number().to_owned())
+            });
+        report.push(EndpointReport::check(
+            "station_realtime",
+            board.map(|res| res.body),
+            schema::STATION_REALTIME,
+        ));
+
+        let train = match train_code {
+            Some(code) => self
+                .get(&self.endpoints.train_realtime(&code))
+                .await
+                .map(|res| res.body),
+            None => Err(anyhow::anyhow!("No train on the board to check")),
+        };
+        report.push(EndpointReport::check(
+            "train_realtime",
+            train,
+            schema::TRAIN_REALTIME,
+        ));
+
+        let request = JourneyRequest::one_way(
+            from,
+            to,
+            self.now() + chrono::Duration::days(1),
+            chrono::Duration::hours(6),
+        );
+        report.push(EndpointReport::check(
+            "find_journeys",
+            self.search(&request).await,
+            schema::JOURNEY_RESULTS,
+        ));
+
+        report
+    }
+
+    /// Post `body` to a BIG endpoint not modeled by the crate, e.g. `BookingManager.svc/GetAvailableTrains`.
+    ///
+    /// `body` must be a JSON object, session signature and source system are added to it.
+    pub async fn authed_post(
+        &self,
+        endpoint: &str,
+        mut body: serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        let signature = self.signature().await?;
+
+        let fields = body
+            .as_object_mut()
+            .context("BIG request body must be a JSON object")?;
+        fields.insert("Signature".to_owned(), signature.into());
+        fields.insert("SourceSystem".to_owned(), SOURCE_SYSTEM.into());
+
+        Ok(serde_json::from_str(
+            &self
+                .post(&self.endpoints.big_rest(endpoint), &body)
+                .await?
+                .body,
+        )?)
     }
 }
 
@@ -160,21 +1010,613 @@ mod tests {
     use std::ops::Add;
 
     use chrono::{Duration, Utc};
+    use wiremock::{
+        matchers::{body_partial_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
 
     use super::*;
 
+    #[tokio::test]
+    async fn deadline_aborts_pending_requests() {
+        let api = ItaloApi::builder()
+            .deadline(std::time::Duration::from_millis(10))
+            .build()
+            .unwrap();
+        let start = tokio::time::Instant::now();
+
+        let res = api
+            .within_deadline(start, async {
+                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                Ok(())
+            })
+            .await;
+        assert_eq!(
+            res.unwrap_err().downcast_ref::<ItaloError>(),
+            Some(&ItaloError::DeadlineExceeded)
+        );
+
+        assert!(api.within_deadline(start, async { Ok(()) }).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn train_events_until_arrival() {
+        let server = MockServer::start().await;
+        let running = include_str!("../tests/fixtures/train_realtime_8158.json");
+        let mut arrived: serde_json::Value = serde_json::from_str(running).unwrap();
+        let schedule = &mut arrived["TrainSchedule"];
+        let mut remaining = schedule["StazioniNonFerme"].take();
+        remaining
+            .as_array_mut()
+            .unwrap()
+            .iter_mut()
+            .for_each(|station| station["ActualArrivalTime"] = "10:08".into());
+        schedule["StazioniFerme"]
+            .as_array_mut()
+            .unwrap()
+            .extend(remaining.as_array().unwrap().iter().cloned());
+        schedule["StazioniNonFerme"] = serde_json::json!([]);
+
+        Mock::given(method("GET"))
+            .and(path("/api/RicercaTrenoService"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(running, "application/json"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/RicercaTrenoService"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(arrived))
+            .mount(&server)
+            .await;
+        let api = fixture::api(&server);
+
+        let events: Vec<_> = api
+            .train_events("8158", std::time::Duration::from_millis(10))
+            .collect()
+            .await;
+        assert_eq!(
+            events,
+            vec![
+                TrainEvent::Arrived {
+                    station: "BO_".to_owned()
+                },
+                TrainEvent::Arrived {
+                    station: "MC_".to_owned()
+                },
+            ]
+        );
+
+        let snapshots: Vec<_> = api
+            .track_until_arrival("8158", std::time::Duration::from_millis(10))
+            .collect()
+            .await;
+        assert_eq!(snapshots.len(), 1);
+        assert!(snapshots[0].as_ref().unwrap().is_completed());
+    }
+
+    #[tokio::test]
+    async fn paused_stream_stops_polling() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/RicercaTrenoService"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                include_str!("../tests/fixtures/train_realtime_8158.json"),
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+        let api = fixture::api(&server);
+        let interval = std::time::Duration::from_millis(10);
+
+        let (control, snapshots) = api.track_until_arrival_controlled("8158", interval);
+        let mut snapshots = Box::pin(snapshots);
+        assert!(snapshots.next().await.unwrap().is_ok());
+
+        control.pause();
+        assert!(control.is_paused());
+        let wait = tokio::time::timeout(interval * 10, snapshots.next()).await;
+        assert!(wait.is_err());
+        let polls = server.received_requests().await.unwrap().len();
+        assert_eq!(polls, 1);
+
+        control.resume();
+        assert!(snapshots.next().await.unwrap().is_ok());
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn reachable_stations_use_cached_list() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/BIG/v7/Rest/SessionManager.svc/Login"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"Signature": "abc"}"#))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/BIG/v7/Rest/BookingManager.svc/GetAvailableTrains"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(include_str!("../tests/fixtures/journey_results.json")),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/it/stazione"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let station = |code: &str, name: &str| Station::new(code.into(), "".into(), name.into());
+        let napoli = station("NAC", "Napoli Centrale");
+        let mut api = fixture::api(&server);
+        api.station_cache = Some((
+            Instant::now(),
+            vec![
+                napoli.clone(),
+                station("RMT", "Roma Termini"),
+                station("MC_", "Milano Centrale"),
+                station("VSL", "Venezia S. Lucia"),
+            ],
+        ));
+
+        for _ in 0..2 {
+            let mut codes: Vec<_> = api
+                .reachable_stations(&napoli)
+                .await
+                .unwrap()
+                .iter()
+                .map(|station| station.code().clone())
+                .collect();
+            codes.sort();
+            assert_eq!(codes, ["MC_", "RMT"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn station_names_from_cache() {
+        let server = MockServer::start().await;
+        let station = |code: &str, name: &str| Station::new(code.into(), "".into(), name.into());
+        let mut api = fixture::api(&server);
+        api.station_cache = Some((
+            Instant::now(),
+            vec![
+                station("RMT", "Roma Termini"),
+                station("MC_", "Milano Centrale"),
+                station("NAC", "Napoli Centrale"),
+            ],
+        ));
+
+        let names = api
+            .resolve_station_names(&["MC_", "RMT", "XXX", "RMT"])
+            .await
+            .unwrap();
+        assert_eq!(names.len(), 2);
+        assert_eq!(names["MC_"], "Milano Centrale");
+        assert_eq!(names["RMT"], "Roma Termini");
+    }
+
+    #[tokio::test]
+    async fn journeys_from_every_city_station() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/BIG/v7/Rest/SessionManager.svc/Login"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"Signature": "abc"}"#))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/BIG/v7/Rest/BookingManager.svc/GetAvailableTrains"))
+            .and(body_partial_json(
+                serde_json::json!({"GetAvailableTrains": {"DepartureStation": "RTB"}}),
+            ))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/BIG/v7/Rest/BookingManager.svc/GetAvailableTrains"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(include_str!("../tests/fixtures/journey_results.json")),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let station = |code: &str, name: &str| Station::new(code.into(), "".into(), name.into());
+        let milano = station("MC_", "Milano Centrale");
+        let mut api = fixture::api(&server);
+        api.station_cache = Some((
+            Instant::now(),
+            vec![
+                station("RMT", "Roma Termini"),
+                station("RTB", "Roma Tiburtina"),
+                milano.clone(),
+            ],
+        ));
+
+        let results = api
+            .find_journeys_from_city("Roma", &milano, &JourneyRequest::default())
+            .await
+            .unwrap();
+        let single: JourneyResults =
+            serde_json::from_str(include_str!("../tests/fixtures/journey_results.json")).unwrap();
+        assert_eq!(results.journeys().count(), single.journeys().count());
+
+        assert!(api
+            .find_journeys_from_city("Torino", &milano, &JourneyRequest::default())
+            .await
+            .is_err());
+
+        // Only Roma Tiburtina, whose search fails
+        let err = api
+            .find_journeys_from_city("Tiburtina", &milano, &JourneyRequest::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ItaloError>(),
+            Some(ItaloError::ServerError { status: 503, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn next_train_with_fixed_clock() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/BIG/v7/Rest/SessionManager.svc/Login"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"Signature": "abc"}"#))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/BIG/v7/Rest/BookingManager.svc/GetAvailableTrains"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(include_str!("../tests/fixtures/journey_results.json")),
+            )
+            .mount(&server)
+            .await;
+
+        let station = |code: &str| Station::new(code.into(), "".into(), code.into());
+        let (napoli, milano) = (station("NAC"), station("MC_"));
+        let api = fixture::api(&server);
+        assert!(api.next_train(&napoli, &milano).await.unwrap().is_none());
+
+        let morning = chrono::DateTime::from_timestamp(1721026800, 0).unwrap();
+        let api = ItaloApi::builder()
+            .endpoints(EndpointConfig::new(&server.uri(), &server.uri()))
+            .clock(FixedClock(morning))
+            .build()
+            .unwrap();
+        assert_eq!(api.now(), morning);
+        let journey = api.next_train(&napoli, &milano).await.unwrap().unwrap();
+        assert!(journey.departure_time().unwrap() > morning);
+    }
+
+    #[tokio::test]
+    async fn explicit_login() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/BIG/v7/Rest/SessionManager.svc/Login"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"Signature": "abc"}"#))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/BIG/v7/Rest/BookingManager.svc/GetAvailableTrains"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(include_str!("../tests/fixtures/journey_results.json")),
+            )
+            .mount(&server)
+            .await;
+        let api = ItaloApi::builder()
+            .endpoints(EndpointConfig::new(&server.uri(), &server.uri()))
+            .auto_login(false)
+            .build()
+            .unwrap();
+
+        let err = api
+            .find_journeys(&JourneyRequest::default())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ItaloError>(),
+            Some(&ItaloError::NotAuthenticated)
+        );
+        assert!(server.received_requests().await.unwrap().is_empty());
+
+        api.login().await.unwrap();
+        api.login().await.unwrap();
+        assert!(api.find_journeys(&JourneyRequest::default()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn login_server_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/BIG/v7/Rest/SessionManager.svc/Login"))
+            .respond_with(
+                ResponseTemplate::new(503)
+                    .set_body_string(include_str!("../tests/fixtures/login_error.json")),
+            )
+            .mount(&server)
+            .await;
+
+        let api = fixture::api(&server);
+        let err = api.login().await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ItaloError>(),
+            Some(ItaloError::ServerError { status: 503, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn oversized_bodies_are_rejected() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/it/stazione"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("x".repeat(2048)))
+            .mount(&server)
+            .await;
+        let api = ItaloApi::builder()
+            .endpoints(EndpointConfig::new(&server.uri(), &server.uri()))
+            .max_response_bytes(1024)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            api.station_list()
+                .await
+                .unwrap_err()
+                .downcast_ref::<ItaloError>(),
+            Some(&ItaloError::ResponseTooLarge(1024))
+        );
+    }
+
+    #[tokio::test]
+    async fn realtime_from_fixtures() {
+        let server = MockServer::start().await;
+        let live = EndpointConfig::default();
+        fixture::serve(
+            &server,
+            "station_realtime_MC_.json",
+            &live.station_realtime("MC_"),
+            "/api/RicercaStazioneService",
+        )
+        .await;
+        fixture::serve(
+            &server,
+            "train_realtime_8158.json",
+            &live.train_realtime("8158"),
+            "/api/RicercaTrenoService",
+        )
+        .await;
+        let api = fixture::api(&server);
+
+        let board = api
+            .station_realtime(Station::new(
+                "MC_".to_string(),
+                "milano-centrale".to_string(),
+                "Milano Centrale".to_string(),
+            ))
+            .await
+            .unwrap();
+        let train = api.train_realtime("8158").await.unwrap();
+
+        if !fixture::capturing() {
+            assert_eq!(board.departures_to("roma").len(), 1);
+            assert_eq!(train.train_schedule().rfi_train_number(), "9958");
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_searches_share_login() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/BIG/v7/Rest/SessionManager.svc/Login"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"Signature": "abc"}"#))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/BIG/v7/Rest/BookingManager.svc/GetAvailableTrains"))
+            .and(body_partial_json(serde_json::json!({"Signature": "abc"})))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(include_str!("../tests/fixtures/journey_results.json")),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let api = fixture::api(&server);
+        let request = JourneyRequest::default();
+        let (first, second) =
+            tokio::join!(api.find_journeys(&request), api.find_journeys(&request));
+        assert_eq!(first.unwrap().journeys().count(), 2);
+        assert_eq!(second.unwrap().journeys().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn refresh_journey_searches_around_departure() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/BIG/v7/Rest/SessionManager.svc/Login"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"Signature": "abc"}"#))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/BIG/v7/Rest/BookingManager.svc/GetAvailableTrains"))
+            .and(body_partial_json(
+                serde_json::json!({"GetAvailableTrains": {
+                    "DepartureStation": "NAC",
+                    "ArrivalStation": "MC_",
+                    "IntervalStartDateTime": "/Date(1721037540000+0000)/",
+                    "IntervalEndDateTime": "/Date(1721037660000+0000)/",
+                    "AdultNumber": 2,
+                    "ChildNumber": 1,
+                    "RoundTrip": false,
+                }}),
+            ))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(include_str!("../tests/fixtures/journey_results.json")),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let api = fixture::api(&server);
+        let results: JourneyResults =
+            serde_json::from_str(include_str!("../tests/fixtures/journey_results.json")).unwrap();
+        let journey = &results.solutions()[0].journeys()[1];
+
+        let mut search = JourneyRequest::default();
+        search.set_adult_number(2).set_child_number(1);
+        search
+            .set_round_trip((
+                true,
+                chrono::DateTime::from_timestamp(1721462400, 0),
+                chrono::DateTime::from_timestamp(1721505600, 0),
+            ))
+            .unwrap();
+
+        let refreshed = api
+            .refresh_journey(journey, &search)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(refreshed.segments()[1].train_number(), "9950");
+
+        let mut gone: serde_json::Value =
+            serde_json::from_str(include_str!("../tests/fixtures/journey_results.json")).unwrap();
+        gone["JourneyDateMarkets"][0]["Journeys"][1]["Segments"][1]["TrainNumber"] = "9952".into();
+        let gone: JourneyResults = serde_json::from_value(gone).unwrap();
+        assert!(api
+            .refresh_journey(&gone.solutions()[0].journeys()[1], &search)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn direct_service_is_cached() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/BIG/v7/Rest/SessionManager.svc/Login"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"Signature": "abc"}"#))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/BIG/v7/Rest/BookingManager.svc/GetAvailableTrains"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(include_str!("../tests/fixtures/journey_results.json")),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut api = fixture::api(&server);
+        let napoli = Station::new(
+            "NAC".into(),
+            "napoli-centrale".into(),
+            "Napoli Centrale".into(),
+        );
+        let milano = Station::new(
+            "MC_".into(),
+            "milano-centrale".into(),
+            "Milano Centrale".into(),
+        );
+        assert!(api.has_direct_service(&napoli, &milano).await.unwrap());
+        assert!(api.has_direct_service(&napoli, &milano).await.unwrap());
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/BIG/v7/Rest/SessionManager.svc/Login"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"Signature": "abc"}"#))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/BIG/v7/Rest/BookingManager.svc/GetAvailableTrains"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string(
+                    include_str!("../tests/fixtures/journey_results.json")
+                        .replace(r#""NoStopTrain": true"#, r#""NoStopTrain": false"#),
+                ),
+            )
+            .mount(&server)
+            .await;
+        let mut api = fixture::api(&server);
+        assert!(!api.has_direct_service(&napoli, &milano).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn server_error_details() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/it/stazione"))
+            .respond_with(
+                ResponseTemplate::new(503)
+                    .insert_header("Retry-After", "120")
+                    .set_body_string(format!("  {}", "x".repeat(500))),
+            )
+            .mount(&server)
+            .await;
+
+        let api = fixture::api(&server);
+        let err = api.station_list().await.unwrap_err();
+        match err.downcast_ref::<ItaloError>() {
+            Some(ItaloError::ServerError {
+                status,
+                retry_after,
+                body_snippet,
+            }) => {
+                assert_eq!(*status, 503);
+                assert_eq!(*retry_after, Some(std::time::Duration::from_secs(120)));
+                assert_eq!(body_snippet.len(), ERROR_BODY_SNIPPET_CHARS + 3);
+                assert!(body_snippet.starts_with('x'));
+            }
+            other => panic!("unexpected error {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn station_list_falls_back_to_cache() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/it/stazione"))
+            .respond_with(ResponseTemplate::new(503).set_body_string("down"))
+            .mount(&server)
+            .await;
+
+        let mut api = fixture::api(&server);
+        assert!(api.station_list_or_cached().await.is_err());
+
+        let milano = Station::new(
+            "MC_".into(),
+            "milano-centrale".into(),
+            "Milano Centrale".into(),
+        );
+        api.station_cache = Some((Instant::now(), vec![milano]));
+        let stations = api.station_list_or_cached().await.unwrap();
+        assert_eq!(stations.len(), 1);
+        assert_eq!(stations[0].code(), "MC_");
+    }
+
     #[tokio::test]
     async fn it_works() {
-        let mut api = ItaloApi::default();
+        let api = ItaloApi::default();
         assert!(!api.is_initialized());
 
-        assert!(api.init().await.is_ok());
+        assert!(api.login().await.is_ok());
         assert!(api.is_initialized());
 
         let stations = api.station_list().await;
         println!("{:?}", stations);
         println!();
-        assert!(stations.is_ok_and(|f| f.len() > 0));
+        assert!(stations.is_ok_and(|f| !f.is_empty()));
 
         let station_realtime = api
             .station_realtime(Station::new(
@@ -186,7 +1628,7 @@ mod tests {
         println!("{:?}", station_realtime);
         println!();
         assert!(station_realtime
-            .is_ok_and(|f| f.arrival_board().len() > 0 && f.departure_board().len() > 0));
+            .is_ok_and(|f| !f.arrival_board().is_empty() && !f.departure_board().is_empty()));
 
         let train_realtime = api.train_realtime("8158").await;
         println!("{:?}", train_realtime);
@@ -205,7 +1647,7 @@ mod tests {
 
         let solutions = api
             .find_journeys(
-                &&JourneyRequest::default()
+                JourneyRequest::default()
                     .set_departure_station(start_station)
                     .set_arrival_station(end_station)
                     .set_interval_start_date_time(Utc::now())
@@ -1,6 +1,32 @@
 use std::ops::Deref;
 
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::ItaloError;
+
+static ERROR_MESSAGE_KEYS: [&str; 4] = ["Message", "ExceptionMessage", "ErrorMessage", "Error"];
+
+/// Parse the login response, error shaped bodies are reported as [`ItaloError::Auth`]
+pub fn parse_login(status: StatusCode, body: &str) -> anyhow::Result<LoginResponse> {
+    if status.is_success() {
+        if let Ok(res) = serde_json::from_str(body) {
+            return Ok(res);
+        }
+    }
+
+    let message = serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|val| {
+            ERROR_MESSAGE_KEYS
+                .iter()
+                .find_map(|key| val.get(key)?.as_str().map(str::to_owned))
+        })
+        .unwrap_or_else(|| format!("unexpected login response ({status})"));
+
+    Err(ItaloError::Auth(message).into())
+}
 
 #[derive(Serialize, Debug)]
 #[serde(rename_all = "PascalCase")]
@@ -22,12 +48,35 @@ impl Default for LoginRequestBody<'_> {
     }
 }
 
-#[derive(Deserialize)]
-#[serde(rename_all = "PascalCase")]
+#[derive(Deserialize, Debug)]
+#[serde(from = "LoginResponseShape")]
 pub struct LoginResponse {
     signature: String,
 }
 
+/// Some channels wrap the signature in a `LoginResponse` object
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LoginResponseShape {
+    Flat {
+        #[serde(rename = "Signature")]
+        signature: String,
+    },
+    Nested {
+        #[serde(rename = "LoginResponse")]
+        inner: Box<LoginResponseShape>,
+    },
+}
+
+impl From<LoginResponseShape> for LoginResponse {
+    fn from(val: LoginResponseShape) -> Self {
+        match val {
+            LoginResponseShape::Flat { signature } => LoginResponse { signature },
+            LoginResponseShape::Nested { inner } => (*inner).into(),
+        }
+    }
+}
+
 impl Deref for LoginResponse {
     type Target = str;
 
@@ -42,3 +91,46 @@ struct LoginRequestInternal<'a> {
     password: &'a str,
     username: &'a str,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn login_responses() {
+        let res = parse_login(StatusCode::OK, r#"{"Signature": "abc"}"#).unwrap();
+        assert_eq!(&*res, "abc");
+
+        let res = parse_login(
+            StatusCode::OK,
+            include_str!("../../tests/fixtures/login.json"),
+        )
+        .unwrap();
+        assert_eq!(&*res, "flat-signature");
+
+        let res = parse_login(
+            StatusCode::OK,
+            include_str!("../../tests/fixtures/login_nested.json"),
+        )
+        .unwrap();
+        assert_eq!(&*res, "nested-signature");
+
+        let err = parse_login(
+            StatusCode::UNAUTHORIZED,
+            include_str!("../../tests/fixtures/login_error.json"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ItaloError>(),
+            Some(&ItaloError::Auth(
+                "The username or password is incorrect".to_owned()
+            ))
+        );
+
+        let err = parse_login(StatusCode::OK, "{}").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ItaloError>(),
+            Some(ItaloError::Auth(_))
+        ));
+    }
+}
@@ -0,0 +1,22 @@
+use std::path::Path;
+
+use chrono::Utc;
+
+static FILE_NAME_PATTERN: &str = "%Y%m%dT%H%M%S%.6f";
+
+/// Write the exchange in `dir`, failures are logged and never returned
+pub async fn record(dir: &Path, url: &str, request: Option<&str>, response: &str) {
+    let method = match request {
+        Some(_) => "POST",
+        None => "GET",
+    };
+    let content = format!(
+        "{method} {url}\n{}\n\n{response}\n",
+        request.unwrap_or_default()
+    );
+    let path = dir.join(Utc::now().format(FILE_NAME_PATTERN).to_string() + ".txt");
+
+    if let Err(err) = tokio::fs::write(&path, content).await {
+        tracing::warn!("Failed to record exchange in {}: {err}", path.display());
+    }
+}
@@ -0,0 +1,374 @@
+use std::collections::BTreeSet;
+
+use getset::Getters;
+use serde_json::Value;
+
+/// Field read by the crate models.
+///
+/// The tables below mirror the serde models, the test suite fails when a model key is missing or stale
+pub struct Field {
+    name: &'static str,
+    optional: bool,
+    nested: &'static [Field],
+}
+
+const fn required(name: &'static str) -> Field {
+    Field {
+        name,
+        optional: false,
+        nested: &[],
+    }
+}
+
+const fn optional(name: &'static str) -> Field {
+    Field {
+        name,
+        optional: true,
+        nested: &[],
+    }
+}
+
+const fn nested(name: &'static str, nested: &'static [Field]) -> Field {
+    Field {
+        name,
+        optional: false,
+        nested,
+    }
+}
+
+const fn optional_nested(name: &'static str, nested: &'static [Field]) -> Field {
+    Field {
+        name,
+        optional: true,
+        nested,
+    }
+}
+
+static BOARD_TRAIN: &[Field] = &[
+    required("Numero"),
+    required("DescrizioneLocalita"),
+    required("OraPassaggio"),
+    required("NuovoOrario"),
+    required("Binario"),
+    required("Descrizione"),
+];
+
+pub static STATION_REALTIME: &[Field] = &[
+    optional_nested("ListaTreniArrivo", BOARD_TRAIN),
+    optional_nested("ListaTreniPartenza", BOARD_TRAIN),
+];
+
+static TRAIN_STATION: &[Field] = &[
+    required("LocationCode"),
+    required("LocationDescription"),
+    required("RfiLocationCode"),
+    required("EstimatedDepartureTime"),
+    required("ActualDepartureTime"),
+    required("EstimatedArrivalTime"),
+    required("ActualArrivalTime"),
+    optional("ActualArrivalPlatform"),
+    optional("ActualDeparturePlatform"),
+    optional("Binario"),
+    required("StationNumber"),
+    optional("Soppressa"),
+    optional("Suppressed"),
+];
+
+static DISRUPTION: &[Field] = &[
+    required("DelayAmount"),
+    required("LocationCode"),
+    required("Warning"),
+    required("RunningState"),
+];
+
+static TRAIN_SCHEDULE: &[Field] = &[
+    required("TrainNumber"),
+    required("RfiTrainNumber"),
+    required("DepartureDate"),
+    required("DepartureStationDescription"),
+    required("ArrivalDate"),
+    required("ArrivalStationDescription"),
+    nested("Distruption", DISRUPTION),
+    nested("StazionePartenza", TRAIN_STATION),
+    nested("StazioniFerme", TRAIN_STATION),
+    nested("StazioniNonFerme", TRAIN_STATION),
+    optional("TrainType"),
+    optional("Replaces"),
+    optional("Sostituisce"),
+    optional("ReplacedBy"),
+    optional("SostituitoDa"),
+];
+
+pub static TRAIN_REALTIME: &[Field] = &[
+    required("LastUpdate"),
+    nested("TrainSchedule", TRAIN_SCHEDULE),
+];
+
+static STOP: &[Field] = &[
+    required("STD"),
+    required("STA"),
+    required("DepartureStation"),
+    required("ArrivalStation"),
+];
+
+static FARE: &[Field] = &[
+    optional("ClassOfService"),
+    optional("ProductClass"),
+    optional("Amount"),
+    optional("AvailableCount"),
+    optional("Refundable"),
+    optional("Changeable"),
+];
+
+static SEGMENT: &[Field] = &[
+    required("STD"),
+    required("STA"),
+    optional("TrainNumber"),
+    optional("NoStopTrain"),
+    nested("Legs", STOP),
+    optional("Amenities"),
+    optional("TrainType"),
+    optional_nested("Fares", FARE),
+];
+
+static SUSTAINABILITY: &[Field] = &[required("Co2Grams"), optional("CarCo2Grams")];
+
+static JOURNEY: &[Field] = &[
+    nested("Segments", SEGMENT),
+    optional_nested("Sustainability", SUSTAINABILITY),
+];
+
+static JOURNEYS_SOLUTION: &[Field] = &[required("DepartureDate"), nested("Journeys", JOURNEY)];
+
+pub static JOURNEY_RESULTS: &[Field] = &[
+    nested("JourneyDateMarkets", JOURNEYS_SOLUTION),
+    optional("Messages"),
+];
+
+/// Differences between the crate models and the italotreno responses, see [`crate::ItaloApi::check_schema()`]
+#[derive(Debug, Clone, Default, Getters)]
+#[get = "pub"]
+pub struct SchemaReport {
+    /// One entry for each checked endpoint
+    endpoints: Vec<EndpointReport>,
+}
+
+impl SchemaReport {
+    pub(crate) fn push(&mut self, report: EndpointReport) {
+        self.endpoints.push(report);
+    }
+
+    /// No endpoint drifted from the crate models
+    pub fn is_clean(&self) -> bool {
+        self.endpoints.iter().all(EndpointReport::is_clean)
+    }
+}
+
+/// Schema check outcome for a single endpoint
+#[derive(Debug, Clone, Getters)]
+#[get = "pub"]
+pub struct EndpointReport {
+    /// Checked endpoint
+    endpoint: String,
+
+    /// The endpoint could not be called or did not return JSON
+    error: Option<String>,
+
+    /// Fields returned by italotreno but ignored by the crate
+    unknown_fields: Vec<String>,
+
+    /// Fields expected by the crate but not returned by italotreno
+    missing_fields: Vec<String>,
+}
+
+impl EndpointReport {
+    /// Compare `body` against `schema`
+    pub(crate) fn check(endpoint: &str, body: anyhow::Result<String>, schema: &[Field]) -> Self {
+        let mut report = EndpointReport {
+            endpoint: endpoint.to_owned(),
+            error: None,
+            unknown_fields: vec![],
+            missing_fields: vec![],
+        };
+
+        match body.and_then(|body| Ok(serde_json::from_str::<Value>(&body)?)) {
+            Ok(value) => {
+                let mut unknown = BTreeSet::new();
+                let mut missing = BTreeSet::new();
+                walk(&value, schema, "", &mut unknown, &mut missing);
+                report.unknown_fields = unknown.into_iter().collect();
+                report.missing_fields = missing.into_iter().collect();
+            }
+            Err(err) => report.error = Some(err.to_string()),
+        }
+        report
+    }
+
+    /// The endpoint answered as expected
+    pub fn is_clean(&self) -> bool {
+        self.error.is_none() && self.unknown_fields.is_empty() && self.missing_fields.is_empty()
+    }
+}
+
+fn walk(
+    value: &Value,
+    schema: &[Field],
+    path: &str,
+    unknown: &mut BTreeSet<String>,
+    missing: &mut BTreeSet<String>,
+) {
+    match value {
+        Value::Array(elems) => elems
+            .iter()
+            .for_each(|elem| walk(elem, schema, &format!("{path}[]"), unknown, missing)),
+        Value::Object(fields) => {
+            let prefix = match path.is_empty() {
+                true => String::new(),
+                false => format!("{path}."),
+            };
+
+            schema
+                .iter()
+                .filter(|field| !field.optional && !fields.contains_key(field.name))
+                .for_each(|field| {
+                    missing.insert(prefix.clone() + field.name);
+                });
+
+            fields.iter().for_each(|(key, val)| {
+                match schema.iter().find(|field| field.name == key) {
+                    None => {
+                        unknown.insert(prefix.clone() + key);
+                    }
+                    Some(field) if !field.nested.is_empty() => {
+                        walk(val, field.nested, &(prefix.clone() + key), unknown, missing)
+                    }
+                    Some(_) => {}
+                }
+            });
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{
+        de::{self, Visitor},
+        forward_to_deserialize_any, Deserialize, Deserializer,
+    };
+
+    use super::*;
+    use crate::{
+        journey::{JourneySegment, Stop, Sustainability},
+        Disruption, Fare, Journey, JourneyResults, JourneysSolution, StationRealtime,
+        StationTrainRealtime, TrainRealtime, TrainSchedule, TrainStation,
+    };
+
+    /// Keys, renames and aliases included, a serde model reads
+    fn model_fields<'de, T: Deserialize<'de>>() -> BTreeSet<&'static str> {
+        struct Recorder<'a>(&'a mut &'static [&'static str]);
+
+        impl<'de> Deserializer<'de> for Recorder<'_> {
+            type Error = de::value::Error;
+
+            fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+                Err(de::Error::custom("not a struct"))
+            }
+
+            fn deserialize_struct<V: Visitor<'de>>(
+                self,
+                _: &'static str,
+                fields: &'static [&'static str],
+                _: V,
+            ) -> Result<V::Value, Self::Error> {
+                *self.0 = fields;
+                Err(de::Error::custom("fields recorded"))
+            }
+
+            forward_to_deserialize_any! {
+                bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+                option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+                ignored_any
+            }
+        }
+
+        let mut fields: &'static [&'static str] = &[];
+        let _ = T::deserialize(Recorder(&mut fields));
+        fields.iter().copied().collect()
+    }
+
+    fn schema_fields(schema: &[Field]) -> BTreeSet<&'static str> {
+        schema.iter().map(|field| field.name).collect()
+    }
+
+    #[test]
+    fn schema_matches_models() {
+        for (name, schema, model) in [
+            (
+                "station",
+                STATION_REALTIME,
+                model_fields::<StationRealtime>(),
+            ),
+            (
+                "board train",
+                BOARD_TRAIN,
+                model_fields::<StationTrainRealtime>(),
+            ),
+            ("train", TRAIN_REALTIME, model_fields::<TrainRealtime>()),
+            ("schedule", TRAIN_SCHEDULE, model_fields::<TrainSchedule>()),
+            (
+                "train station",
+                TRAIN_STATION,
+                model_fields::<TrainStation>(),
+            ),
+            ("disruption", DISRUPTION, model_fields::<Disruption>()),
+            (
+                "journeys",
+                JOURNEY_RESULTS,
+                model_fields::<JourneyResults>(),
+            ),
+            (
+                "solution",
+                JOURNEYS_SOLUTION,
+                model_fields::<JourneysSolution>(),
+            ),
+            ("journey", JOURNEY, model_fields::<Journey>()),
+            (
+                "sustainability",
+                SUSTAINABILITY,
+                model_fields::<Sustainability>(),
+            ),
+            ("segment", SEGMENT, model_fields::<JourneySegment>()),
+            ("stop", STOP, model_fields::<Stop>()),
+            ("fare", FARE, model_fields::<Fare>()),
+        ] {
+            assert!(!model.is_empty(), "{name}");
+            assert_eq!(schema_fields(schema), model, "{name}");
+        }
+    }
+
+    #[test]
+    fn report_unknown_and_missing_fields() {
+        let body = include_str!("../../tests/fixtures/station_realtime_MC_.json")
+            .replacen(r#""Binario": "12","#, r#""Binario": "12", "Note": "","#, 1)
+            .replacen(r#""Descrizione": "In orario""#, r#""Extra": true"#, 1);
+        let report = EndpointReport::check("station", Ok(body), STATION_REALTIME);
+
+        assert!(!report.is_clean());
+        assert_eq!(
+            report.unknown_fields(),
+            &["ListaTreniArrivo[].Extra", "ListaTreniArrivo[].Note"]
+        );
+        assert_eq!(report.missing_fields(), &["ListaTreniArrivo[].Descrizione"]);
+
+        let report = EndpointReport::check(
+            "train",
+            Ok(include_str!("../../tests/fixtures/train_realtime_8158.json").to_owned()),
+            TRAIN_REALTIME,
+        );
+        assert!(report.is_clean(), "{report:?}");
+
+        let report = EndpointReport::check("journeys", Ok("<html>".to_owned()), JOURNEY_RESULTS);
+        assert!(report.error().is_some());
+    }
+}
@@ -1,6 +1,140 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
 use derive_new::new;
-use getset::Getters;
-use serde::Deserialize;
+use getset::{Getters, Setters};
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    error::retain_decodable,
+    time::{delay_minutes, parse_realtime_time, rome_date, wrap_day_minutes},
+    ParseWarning,
+};
+
+static EARTH_RADIUS_KM: f64 = 6371.;
+static BOARD_KEYS: [&str; 2] = ["ListaTreniArrivo", "ListaTreniPartenza"];
+static AT_PLATFORM_WINDOW_MINUTES: i64 = 5;
+static CANCELLED_STEMS: [&str; 3] = ["cancel", "soppress", "suppress"];
+static ON_TIME_PHRASES: [&str; 6] = [
+    "in orario",
+    "on time",
+    "nessun ritardo",
+    "senza ritardo",
+    "no delay",
+    "not delayed",
+];
+static DELAYED_STEMS: [&str; 2] = ["ritard", "delay"];
+static DELAYED_WORDS: [&str; 1] = ["late"];
+static STATION_LIST_MISSING: [&str; 3] = [
+    "stationList not found",
+    "stationCoding not found",
+    "localization not found",
+];
+
+/// Strings locating the station lists inside the italoinviaggio station page.
+///
+/// Advanced escape hatch: the defaults match the current page, override them through
+/// [`crate::ItaloApiBuilder::station_list_markers()`] only to patch around a renamed JavaScript variable
+/// until a new release catches up
+#[derive(Debug, Clone, PartialEq, Eq, Getters, Setters)]
+#[getset(get = "pub", set = "pub")]
+pub struct StationListMarkers {
+    /// Precedes the station labels list
+    labels: String,
+
+    /// Precedes the station codes list
+    codes: String,
+
+    /// Follows the station codes list
+    end: String,
+}
+
+impl Default for StationListMarkers {
+    fn default() -> Self {
+        Self {
+            labels: "ItaloInViaggio.Resources.stationList = ".to_owned(),
+            codes: "ItaloInViaggio.Resources.stationCoding = ".to_owned(),
+            end: "ItaloInViaggio.Resources.localizzation".to_owned(),
+        }
+    }
+}
+
+/// Extract the stations from the italoinviaggio station page.
+///
+/// The labels and codes lists are the JavaScript assignments between the markers, located with a single
+/// forward scan of the page and deserialized in place
+pub(crate) fn parse_station_list(
+    page: &str,
+    markers: &StationListMarkers,
+) -> anyhow::Result<Vec<Station>> {
+    let (labels, codes) = station_list_blobs(page, markers)?;
+    Ok(join_station_lists(
+        serde_json::from_str(labels)?,
+        serde_json::from_str(codes)?,
+    ))
+}
+
+/// Same as [`parse_station_list()`] dropping the list entries that fail to decode instead of failing the whole list
+pub(crate) fn parse_station_list_lenient(
+    page: &str,
+    markers: &StationListMarkers,
+) -> anyhow::Result<(Vec<Station>, Vec<ParseWarning>)> {
+    let (labels, codes) = station_list_blobs(page, markers)?;
+    let mut warnings = Vec::new();
+    let mut labels: Vec<serde_json::Value> = serde_json::from_str(labels)?;
+    retain_decodable::<StationLabel>(&mut labels, "stationList", &mut warnings);
+    let mut codes: Vec<serde_json::Value> = serde_json::from_str(codes)?;
+    retain_decodable::<StationCode>(&mut codes, "stationCoding", &mut warnings);
+
+    Ok((
+        join_station_lists(
+            serde_json::from_value(labels.into())?,
+            serde_json::from_value(codes.into())?,
+        ),
+        warnings,
+    ))
+}
+
+/// Labels and codes lists of the station page, see [`StationListMarkers`]
+fn station_list_blobs<'a>(
+    page: &'a str,
+    markers: &StationListMarkers,
+) -> anyhow::Result<(&'a str, &'a str)> {
+    let markers = [&markers.labels, &markers.codes, &markers.end];
+    let mut offsets = [0; 3];
+    let mut pos = 0;
+    for ((marker, missing), offset) in markers.iter().zip(STATION_LIST_MISSING).zip(&mut offsets) {
+        pos += page[pos..].find(marker.as_str()).context(missing)?;
+        *offset = pos;
+        pos += marker.len();
+    }
+    let blob = |index: usize| {
+        page[offsets[index] + markers[index].len()..offsets[index + 1]]
+            .trim_end()
+            .trim_end_matches(';')
+    };
+
+    Ok((blob(0), blob(1)))
+}
+
+fn join_station_lists(label_list: Vec<StationLabel>, code_list: Vec<StationCode>) -> Vec<Station> {
+    let label_map = label_list
+        .into_iter()
+        .map(|elem| (elem.value, elem.label))
+        .collect::<HashMap<_, _>>();
+
+    code_list
+        .into_iter()
+        .filter_map(|elem| {
+            let name = label_map.get(&elem.code).filter(|name| !name.is_empty())?;
+            Some(
+                Station::new(elem.code, elem.url_coding, name.clone())
+                    .with_coordinates(elem.latitude, elem.longitude),
+            )
+        })
+        .collect()
+}
 
 #[derive(Deserialize, Debug, Getters)]
 #[serde(rename_all = "camelCase")]
@@ -8,6 +142,10 @@ use serde::Deserialize;
 pub struct StationCode {
     code: String,
     url_coding: String,
+    #[serde(default, alias = "lat")]
+    latitude: Option<f64>,
+    #[serde(default, alias = "lon", alias = "lng")]
+    longitude: Option<f64>,
 }
 
 #[derive(Deserialize, Debug, Getters)]
@@ -18,7 +156,7 @@ pub struct StationLabel {
 }
 
 /// Station metadata
-#[derive(Debug, Getters, new)]
+#[derive(Debug, Clone, Getters, new)]
 #[get = "pub"]
 pub struct Station {
     /// Internal italotreno ID
@@ -29,36 +167,364 @@ pub struct Station {
 
     /// Human friendly station name
     name: String,
+
+    /// Station latitude, if published by italotreno
+    #[new(default)]
+    latitude: Option<f64>,
+
+    /// Station longitude, if published by italotreno
+    #[new(default)]
+    longitude: Option<f64>,
+}
+
+impl Station {
+    /// Check the shape of an italo station code: 3 uppercase letters, digits or underscores, like `NAC` or `MC_`
+    pub fn validate_code(code: &str) -> anyhow::Result<()> {
+        let valid = code.len() == 3
+            && code
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+        match (valid, code.len()) {
+            (true, _) => Ok(()),
+            (false, 1 | 2) => Err(anyhow!(
+                "Invalid station code {code:?}, shorter codes are padded with underscores like \"{}\"",
+                format!("{code:_<3}").to_uppercase()
+            )),
+            (false, _) => Err(anyhow!(
+                "Invalid station code {code:?}, expected 3 uppercase characters like \"MC_\""
+            )),
+        }
+    }
+
+    pub(crate) fn with_coordinates(
+        mut self,
+        latitude: Option<f64>,
+        longitude: Option<f64>,
+    ) -> Self {
+        self.latitude = latitude;
+        self.longitude = longitude;
+        self
+    }
+
+    /// Great-circle distance (in km) from the given coordinates, `None` when the station has no coordinates
+    pub fn distance_km(&self, latitude: f64, longitude: f64) -> Option<f64> {
+        let (lat, lon) = (self.latitude?, self.longitude?);
+        let d_lat = (latitude - lat).to_radians() / 2.;
+        let d_lon = (longitude - lon).to_radians() / 2.;
+        let a = d_lat.sin().powi(2)
+            + lat.to_radians().cos() * latitude.to_radians().cos() * d_lon.sin().powi(2);
+        Some(2. * EARTH_RADIUS_KM * a.sqrt().asin())
+    }
+}
+
+/// Outcome of a station search by name
+#[derive(Debug, Clone)]
+pub enum StationLookup {
+    /// A single station matches
+    Found(Station),
+
+    /// Several stations match, the caller has to pick one
+    Ambiguous(Vec<Station>),
+
+    /// No station matches
+    NotFound,
+}
+
+impl StationLookup {
+    /// Search `name` among `stations` ignoring case. An exact match wins over partial ones.
+    pub(crate) fn search(stations: Vec<Station>, name: &str) -> Self {
+        let name = name.trim().to_lowercase();
+        let mut matches: Vec<Station> = stations
+            .into_iter()
+            .filter(|elem| elem.name.to_lowercase().contains(&name))
+            .collect();
+
+        if let Some(pos) = matches
+            .iter()
+            .position(|elem| elem.name.to_lowercase() == name)
+        {
+            return StationLookup::Found(matches.swap_remove(pos));
+        }
+
+        match matches.len() {
+            0 => StationLookup::NotFound,
+            1 => StationLookup::Found(matches.remove(0)),
+            _ => StationLookup::Ambiguous(matches),
+        }
+    }
+
+    /// Stations matching the search: the found one, every ambiguous candidate or none
+    pub fn into_candidates(self) -> Vec<Station> {
+        match self {
+            StationLookup::Found(station) => vec![station],
+            StationLookup::Ambiguous(candidates) => candidates,
+            StationLookup::NotFound => Vec::new(),
+        }
+    }
 }
 
-/// Abstraction over departure and departure board for a station
+/// Abstraction over arrival and departure board for a station, a board missing from the response is empty
 #[derive(Deserialize, Debug, Getters)]
+#[cfg_attr(feature = "binary-cache", derive(serde::Serialize))]
 #[get = "pub"]
 pub struct StationRealtime {
-    #[serde(rename(deserialize = "ListaTreniArrivo"))]
+    #[serde(
+        default,
+        rename(deserialize = "ListaTreniArrivo"),
+        deserialize_with = "arrival_board"
+    )]
     arrival_board: Vec<StationTrainRealtime>,
 
-    #[serde(rename(deserialize = "ListaTreniPartenza"))]
+    #[serde(
+        default,
+        rename(deserialize = "ListaTreniPartenza"),
+        deserialize_with = "departure_board"
+    )]
     departure_board: Vec<StationTrainRealtime>,
 }
 
+/// Parse a station board dropping the entries that fail to decode instead of failing the whole board
+pub(crate) fn parse_station_realtime_lenient(
+    body: &str,
+) -> anyhow::Result<(StationRealtime, Vec<ParseWarning>)> {
+    let mut value: serde_json::Value = serde_json::from_str(body)?;
+    let mut warnings = Vec::new();
+
+    for key in BOARD_KEYS {
+        if let Some(serde_json::Value::Array(entries)) = value.get_mut(key) {
+            retain_decodable::<StationTrainRealtime>(entries, key, &mut warnings);
+        }
+    }
+
+    Ok((serde_json::from_value(value)?, warnings))
+}
+
+fn board<'de, D>(deserializer: D, kind: BoardKind) -> Result<Vec<StationTrainRealtime>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut board = Vec::<StationTrainRealtime>::deserialize(deserializer)?;
+    board.iter_mut().for_each(|elem| elem.kind = kind);
+    Ok(board)
+}
+
+fn arrival_board<'de, D>(deserializer: D) -> Result<Vec<StationTrainRealtime>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    board(deserializer, BoardKind::Arrival)
+}
+
+fn departure_board<'de, D>(deserializer: D) -> Result<Vec<StationTrainRealtime>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    board(deserializer, BoardKind::Departure)
+}
+
+fn diff_board<'a>(
+    old: &'a [StationTrainRealtime],
+    new: &'a [StationTrainRealtime],
+) -> impl Iterator<Item = BoardDiff> + 'a {
+    let find = |board: &'a [StationTrainRealtime], number: &str| {
+        board.iter().find(|elem| elem.number == number)
+    };
+
+    let removed = old
+        .iter()
+        .filter(move |elem| find(new, &elem.number).is_none())
+        .map(|elem| BoardDiff::Removed {
+            train: elem.number.clone(),
+        });
+
+    let changed = new.iter().flat_map(move |elem| {
+        let train = elem.number.clone();
+        match find(old, &elem.number) {
+            None => vec![BoardDiff::Added { train }],
+            Some(prev) => {
+                let mut diffs = vec![];
+                if prev.platform != elem.platform {
+                    diffs.push(BoardDiff::PlatformChanged {
+                        train: train.clone(),
+                        old: prev.platform.clone(),
+                        new: elem.platform.clone(),
+                    });
+                }
+                if prev.forecast_time != elem.forecast_time {
+                    diffs.push(BoardDiff::ForecastChanged {
+                        train,
+                        old: prev.forecast_time.clone(),
+                        new: elem.forecast_time.clone(),
+                    });
+                }
+                diffs
+            }
+        }
+    });
+
+    removed.chain(changed)
+}
+
+/// Change detected between two snapshots of a station board
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BoardDiff {
+    /// The train appeared on the board
+    Added {
+        /// Train number
+        train: String,
+    },
+
+    /// The train left the board
+    Removed {
+        /// Train number
+        train: String,
+    },
+
+    /// The train has been assigned to a different platform
+    PlatformChanged {
+        /// Train number
+        train: String,
+        /// Previous platform
+        old: String,
+        /// Current platform
+        new: String,
+    },
+
+    /// The forecast time of the train changed
+    ForecastChanged {
+        /// Train number
+        train: String,
+        /// Previous forecast time
+        old: String,
+        /// Current forecast time
+        new: String,
+    },
+}
+
+/// Station board entry placed on the station timeline
+#[derive(Debug, Clone, Copy)]
+pub struct TimelineEntry<'a> {
+    time: Option<DateTime<Utc>>,
+    train: &'a StationTrainRealtime,
+}
+
+impl<'a> TimelineEntry<'a> {
+    /// Arrival or departure
+    pub fn kind(&self) -> BoardKind {
+        self.train.kind
+    }
+
+    /// Parsed passage time
+    pub fn time(&self) -> Option<DateTime<Utc>> {
+        self.time
+    }
+
+    /// Board entry
+    pub fn train(&self) -> &'a StationTrainRealtime {
+        self.train
+    }
+}
+
+/// Board on which a [`StationTrainRealtime`] is listed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BoardKind {
+    /// Arrival board
+    Arrival,
+
+    /// Departure board
+    #[default]
+    Departure,
+}
+
+impl StationRealtime {
+    /// Arrivals and departures merged in chronological order, entries without a valid time come last.
+    ///
+    /// Board times are placed on the italian date of `now`, see [`crate::ItaloApi::now()`]
+    pub fn timeline(&self, now: DateTime<Utc>) -> Vec<TimelineEntry<'_>> {
+        let today = rome_date(now);
+        let mut timeline: Vec<TimelineEntry> = self
+            .arrival_board
+            .iter()
+            .chain(&self.departure_board)
+            .map(|train| TimelineEntry {
+                time: parse_realtime_time(&train.passage_time, today).ok(),
+                train,
+            })
+            .collect();
+
+        timeline.sort_by_key(|elem| (elem.time.is_none(), elem.time));
+        timeline
+    }
+
+    /// Changes between this board and a `newer` one of the same station
+    pub fn diff(&self, newer: &StationRealtime) -> Vec<BoardDiff> {
+        diff_board(&self.arrival_board, &newer.arrival_board)
+            .chain(diff_board(&self.departure_board, &newer.departure_board))
+            .collect()
+    }
+
+    /// Departing trains whose destination contains `destination`, ignoring case
+    pub fn departures_to(&self, destination: &str) -> Vec<&StationTrainRealtime> {
+        let destination = destination.to_lowercase();
+        self.departure_board
+            .iter()
+            .filter(|elem| elem.destination.to_lowercase().contains(&destination))
+            .collect()
+    }
+
+    /// Trains likely standing at their platform right now, see [`Self::trains_at_platform_at()`].
+    ///
+    /// Reads the system clock: pass [`crate::ItaloApi::now()`] to the `_at` variant to honour a custom clock
+    pub fn trains_at_platform_now(&self) -> Vec<&StationTrainRealtime> {
+        self.trains_at_platform_at(Utc::now())
+    }
+
+    /// Trains likely standing at their platform at the time `now`, see [`crate::ItaloApi::now()`]: a platform
+    /// is assigned and the forecast time (the scheduled one without a forecast) is within a few minutes of `now`.
+    ///
+    /// Trains listed on both boards are returned once
+    pub fn trains_at_platform_at(&self, now: DateTime<Utc>) -> Vec<&StationTrainRealtime> {
+        let today = rome_date(now);
+        let mut listed = HashSet::new();
+        self.arrival_board
+            .iter()
+            .chain(&self.departure_board)
+            .filter(|train| !train.platform.trim().is_empty())
+            .filter(|train| {
+                let time = match train.forecast_time.trim() {
+                    "" => &train.passage_time,
+                    forecast => forecast,
+                };
+                parse_realtime_time(time, today).is_ok_and(|time| {
+                    wrap_day_minutes((time - now).num_minutes()).abs() <= AT_PLATFORM_WINDOW_MINUTES
+                })
+            })
+            .filter(|train| listed.insert(&train.number))
+            .collect()
+    }
+}
+
 /// Train data during its stay at the station
 #[derive(Deserialize, Debug, Getters)]
+#[cfg_attr(feature = "binary-cache", derive(serde::Serialize))]
 #[get = "pub"]
 pub struct StationTrainRealtime {
     /// Train number
     #[serde(rename(deserialize = "Numero"))]
     number: String,
 
-    /// Train end point
+    /// Location shown by the board: the origin on arrival boards, the terminus on departure boards.
+    ///
+    /// Prefer [`Self::origin()`] and [`Self::terminus()`]
     #[serde(rename(deserialize = "DescrizioneLocalita"))]
     destination: String,
 
-    /// Scheduled departure time
+    /// Scheduled passage time: the departure for trains leaving from their origin,
+    /// the arrival for trains ending here and the stop time for intermediate stations
     #[serde(rename(deserialize = "OraPassaggio"))]
-    scheduled_time: String,
+    passage_time: String,
 
-    /// Real departure time
+    /// Forecast passage time, see [`Self::passage_time()`]
     #[serde(rename(deserialize = "NuovoOrario"))]
     forecast_time: String,
 
@@ -69,4 +535,491 @@ pub struct StationTrainRealtime {
     /// Generic trip description
     #[serde(rename(deserialize = "Descrizione"))]
     description: String,
+
+    /// Board listing the train
+    #[serde(skip)]
+    kind: BoardKind,
+}
+
+impl StationTrainRealtime {
+    /// Scheduled passage time
+    #[deprecated(note = "OraPassaggio is not always a departure time, use `passage_time()`")]
+    pub fn scheduled_time(&self) -> &String {
+        &self.passage_time
+    }
+
+    /// Station the train comes from, only known for arrival board entries
+    pub fn origin(&self) -> Option<&str> {
+        match self.kind {
+            BoardKind::Arrival => Some(&self.destination),
+            BoardKind::Departure => None,
+        }
+    }
+
+    /// Station where the train ends its trip, only known for departure board entries
+    pub fn terminus(&self) -> Option<&str> {
+        match self.kind {
+            BoardKind::Arrival => None,
+            BoardKind::Departure => Some(&self.destination),
+        }
+    }
+
+    /// Train status parsed from [`Self::description`]
+    pub fn status(&self) -> TrainBoardStatus {
+        TrainBoardStatus::from(self.description.as_str())
+    }
+
+    /// The entry carries live data rather than the bare timetable.
+    ///
+    /// True when a forecast is populated and either moves the passage time or comes with a recognized status
+    pub fn has_realtime(&self) -> bool {
+        let forecast = self.forecast_time.trim();
+        !forecast.is_empty()
+            && (forecast != self.passage_time.trim()
+                || !matches!(self.status(), TrainBoardStatus::Unknown(_)))
+    }
+
+    /// Minutes between the scheduled and the forecast passage time, `None` without a parsable forecast
+    pub fn delay_minutes(&self) -> Option<i64> {
+        delay_minutes(&self.passage_time, &self.forecast_time)
+    }
+
+    /// Severity of the delay according to `thresholds`, entries without a forecast are on time
+    pub fn classify_delay(&self, thresholds: &DelayThresholds) -> DelayClass {
+        thresholds.classify(self.delay_minutes().unwrap_or_default())
+    }
+}
+
+/// Severity of a delay, see [`StationTrainRealtime::classify_delay()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DelayClass {
+    /// Running on schedule or nearly so
+    OnTime,
+
+    /// Slightly late
+    Minor,
+
+    /// Significantly late
+    Major,
+
+    /// Severely late
+    Severe,
+}
+
+/// Minutes of delay separating the [`DelayClass`]es, each bound is the first delay of the next class.
+///
+/// The defaults follow the italo boards: on time below 5 minutes, minor delay below 15, major below 60
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Getters, Setters)]
+#[getset(get = "pub", set = "pub")]
+pub struct DelayThresholds {
+    /// First delay classified as [`DelayClass::Minor`]
+    minor: i64,
+
+    /// First delay classified as [`DelayClass::Major`]
+    major: i64,
+
+    /// First delay classified as [`DelayClass::Severe`]
+    severe: i64,
+}
+
+impl Default for DelayThresholds {
+    fn default() -> Self {
+        Self {
+            minor: 5,
+            major: 15,
+            severe: 60,
+        }
+    }
+}
+
+impl DelayThresholds {
+    /// Class of a delay (in minutes), early trains are on time
+    pub fn classify(&self, delay_minutes: i64) -> DelayClass {
+        match delay_minutes {
+            delay if delay >= self.severe => DelayClass::Severe,
+            delay if delay >= self.major => DelayClass::Major,
+            delay if delay >= self.minor => DelayClass::Minor,
+            _ => DelayClass::OnTime,
+        }
+    }
+}
+
+/// Train status as reported by the station board
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrainBoardStatus {
+    /// The train is running on schedule
+    OnTime,
+
+    /// The train is running late
+    Delayed,
+
+    /// The train has been cancelled or suppressed
+    Cancelled,
+
+    /// Status text not recognized
+    Unknown(String),
+}
+
+impl From<&str> for TrainBoardStatus {
+    fn from(val: &str) -> Self {
+        // Stems match any inflection ("soppressa", "ritardato"), negated delays like "nessun ritardo" are on time
+        let text = val.to_lowercase();
+        let words: Vec<_> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect();
+        let padded = format!(" {} ", words.join(" "));
+        let has_phrase = |phrase: &&str| padded.contains(&format!(" {phrase} "));
+        let has_stem = |stem: &&str| padded.contains(&format!(" {stem}"));
+
+        if CANCELLED_STEMS.iter().any(has_stem) {
+            TrainBoardStatus::Cancelled
+        } else if ON_TIME_PHRASES.iter().any(has_phrase) {
+            TrainBoardStatus::OnTime
+        } else if DELAYED_STEMS.iter().any(has_stem) || DELAYED_WORDS.iter().any(has_phrase) {
+            TrainBoardStatus::Delayed
+        } else {
+            TrainBoardStatus::Unknown(val.to_owned())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use chrono_tz::Europe::Rome;
+
+    use super::*;
+
+    static BOARD: &str = r#"{
+        "ListaTreniArrivo": [{
+            "Numero": "8158",
+            "DescrizioneLocalita": "Napoli Centrale",
+            "OraPassaggio": "10:05",
+            "NuovoOrario": "10:05",
+            "Binario": "12",
+            "Descrizione": "In orario"
+        }],
+        "ListaTreniPartenza": [{
+            "Numero": "9941",
+            "DescrizioneLocalita": "Torino Porta Nuova",
+            "OraPassaggio": "10:15",
+            "NuovoOrario": "10:25",
+            "Binario": "7",
+            "Descrizione": "Ritardo 10 minuti"
+        }]
+    }"#;
+
+    #[test]
+    fn board_entries_know_their_board() {
+        let board: StationRealtime = serde_json::from_str(BOARD).unwrap();
+
+        let arrival = &board.arrival_board()[0];
+        assert_eq!(arrival.kind(), &BoardKind::Arrival);
+        assert_eq!(arrival.origin(), Some("Napoli Centrale"));
+        assert_eq!(arrival.terminus(), None);
+
+        let departure = &board.departure_board()[0];
+        assert_eq!(departure.origin(), None);
+        assert_eq!(departure.terminus(), Some("Torino Porta Nuova"));
+        assert_eq!(departure.status(), TrainBoardStatus::Delayed);
+    }
+
+    #[test]
+    fn board_status_text() {
+        for (text, status) in [
+            ("In orario", TrainBoardStatus::OnTime),
+            ("ON TIME", TrainBoardStatus::OnTime),
+            ("Nessun ritardo", TrainBoardStatus::OnTime),
+            ("In ritardo di 10 min.", TrainBoardStatus::Delayed),
+            ("Ritardo 5'", TrainBoardStatus::Delayed),
+            ("Running late", TrainBoardStatus::Delayed),
+            ("Ritardato", TrainBoardStatus::Delayed),
+            ("Treno soppresso", TrainBoardStatus::Cancelled),
+            ("Corsa soppressa", TrainBoardStatus::Cancelled),
+            ("Cancellata", TrainBoardStatus::Cancelled),
+            ("Cancelled", TrainBoardStatus::Cancelled),
+            ("Senza ritardo", TrainBoardStatus::OnTime),
+        ] {
+            assert_eq!(TrainBoardStatus::from(text), status, "{text}");
+        }
+
+        for text in ["", "Latency", "Imbarco"] {
+            assert_eq!(
+                TrainBoardStatus::from(text),
+                TrainBoardStatus::Unknown(text.to_owned()),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn lenient_board_skips_broken_entries() {
+        let mut board: serde_json::Value = serde_json::from_str(BOARD).unwrap();
+        board["ListaTreniPartenza"]
+            .as_array_mut()
+            .unwrap()
+            .push(serde_json::json!({ "Numero": 42 }));
+
+        assert!(serde_json::from_value::<StationRealtime>(board.clone()).is_err());
+
+        let (board, warnings) = parse_station_realtime_lenient(&board.to_string()).unwrap();
+        assert_eq!(board.departure_board().len(), 1);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].list(), "ListaTreniPartenza");
+        assert_eq!(warnings[0].index(), &1);
+    }
+
+    #[test]
+    fn missing_board_is_empty() {
+        let board: StationRealtime = serde_json::from_str(include_str!(
+            "../../tests/fixtures/station_realtime_no_arrivals.json"
+        ))
+        .unwrap();
+        assert!(board.arrival_board().is_empty());
+        assert_eq!(board.departure_board().len(), 1);
+        assert_eq!(board.departure_board()[0].kind(), &BoardKind::Departure);
+    }
+
+    #[test]
+    fn live_board_entries() {
+        let board: StationRealtime = serde_json::from_str(BOARD).unwrap();
+        assert!(board.arrival_board()[0].has_realtime());
+        assert!(board.departure_board()[0].has_realtime());
+
+        let scheduled: StationTrainRealtime = serde_json::from_str(
+            r#"{"Numero": "9989", "DescrizioneLocalita": "Venezia S. Lucia", "OraPassaggio": "18:05", "NuovoOrario": "", "Binario": "", "Descrizione": ""}"#,
+        )
+        .unwrap();
+        assert!(!scheduled.has_realtime());
+
+        let copied: StationTrainRealtime = serde_json::from_str(
+            r#"{"Numero": "9989", "DescrizioneLocalita": "Venezia S. Lucia", "OraPassaggio": "18:05", "NuovoOrario": "18:05", "Binario": "", "Descrizione": ""}"#,
+        )
+        .unwrap();
+        assert!(!copied.has_realtime());
+    }
+
+    #[test]
+    fn station_page() {
+        let page = r#"<script>
+            ItaloInViaggio.Resources.stationList = [{"label": "Milano Centrale", "value": "MC_"}, {"label": "Napoli Centrale", "value": "NAC"}];
+            ItaloInViaggio.Resources.stationCoding = [{"code": "MC_", "urlCoding": "milano-centrale", "lat": 45.48, "lon": 9.2}, {"code": "NAC", "urlCoding": "napoli-centrale"}, {"code": "XXX", "urlCoding": "unknown"}];
+            ItaloInViaggio.Resources.localizzation = {};
+        </script>"#;
+
+        let markers = StationListMarkers::default();
+        let stations = parse_station_list(page, &markers).unwrap();
+        assert_eq!(stations.len(), 2);
+        assert_eq!(stations[0].code(), "MC_");
+        assert_eq!(stations[0].name(), "Milano Centrale");
+        assert_eq!(stations[0].latitude(), &Some(45.48));
+        assert_eq!(stations[1].url_coding(), "napoli-centrale");
+        assert_eq!(stations[1].longitude(), &None);
+
+        let err =
+            parse_station_list(&page.replace("localizzation", "other"), &markers).unwrap_err();
+        assert_eq!(err.to_string(), "localization not found");
+        let err = parse_station_list(&page.replace("stationList", "other"), &markers).unwrap_err();
+        assert_eq!(err.to_string(), "stationList not found");
+
+        let renamed = page.replace("stationList", "stationLabels");
+        let mut markers = StationListMarkers::default();
+        markers.set_labels("ItaloInViaggio.Resources.stationLabels = ".to_owned());
+        assert_eq!(parse_station_list(&renamed, &markers).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn lenient_station_list_skips_broken_entries() {
+        let page = r#"
+            ItaloInViaggio.Resources.stationList = [{"label": "Milano Centrale", "value": "MC_"}, {"label": 7}];
+            ItaloInViaggio.Resources.stationCoding = [{"code": "MC_", "urlCoding": "milano-centrale"}, {"code": "NAC"}];
+            ItaloInViaggio.Resources.localizzation = {};
+        "#;
+        let markers = StationListMarkers::default();
+        assert!(parse_station_list(page, &markers).is_err());
+
+        let (stations, warnings) = parse_station_list_lenient(page, &markers).unwrap();
+        assert_eq!(stations.len(), 1);
+        assert_eq!(stations[0].code(), "MC_");
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].list(), "stationList");
+        assert_eq!(warnings[0].index(), &1);
+        assert_eq!(warnings[1].list(), "stationCoding");
+        assert_eq!(warnings[1].index(), &1);
+    }
+
+    #[test]
+    fn trains_at_platform() {
+        let board: StationRealtime = serde_json::from_str(BOARD).unwrap();
+        let at = |hour, min| {
+            board
+                .trains_at_platform_at(
+                    Rome.with_ymd_and_hms(2024, 7, 15, hour, min, 0)
+                        .unwrap()
+                        .to_utc(),
+                )
+                .iter()
+                .map(|train| train.number().as_str())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(at(10, 6), ["8158"]);
+        assert_eq!(at(10, 22), ["9941"]);
+        assert!(at(13, 0).is_empty());
+    }
+
+    #[test]
+    fn delay_classes() {
+        let board: StationRealtime = serde_json::from_str(BOARD).unwrap();
+        let defaults = DelayThresholds::default();
+        let entry = board
+            .arrival_board()
+            .iter()
+            .chain(board.departure_board())
+            .find(|entry| entry.delay_minutes() == Some(10))
+            .unwrap();
+        assert_eq!(entry.classify_delay(&defaults), DelayClass::Minor);
+
+        let mut strict = defaults;
+        strict.set_minor(3).set_major(10);
+        assert_eq!(entry.classify_delay(&strict), DelayClass::Major);
+
+        assert_eq!(defaults.classify(-2), DelayClass::OnTime);
+        assert_eq!(defaults.classify(4), DelayClass::OnTime);
+        assert_eq!(defaults.classify(90), DelayClass::Severe);
+    }
+
+    #[test]
+    fn station_code_shape() {
+        for code in ["MC_", "NAC", "BO_", "F6_"] {
+            assert!(Station::validate_code(code).is_ok(), "{code}");
+        }
+
+        let err = Station::validate_code("mc").unwrap_err().to_string();
+        assert!(err.contains("\"MC_\""), "{err}");
+        for code in ["", "MC__", "Milano", "mc_", "M-C"] {
+            assert!(Station::validate_code(code).is_err(), "{code}");
+        }
+    }
+
+    #[test]
+    fn haversine_distance() {
+        let milano = Station::new(
+            "MC_".into(),
+            "milano-centrale".into(),
+            "Milano Centrale".into(),
+        )
+        .with_coordinates(Some(45.4862), Some(9.2046));
+        let distance = milano.distance_km(40.8526, 14.2721).unwrap();
+        assert!((655. ..665.).contains(&distance));
+
+        let unknown = Station::new(
+            "NAC".into(),
+            "napoli-centrale".into(),
+            "Napoli Centrale".into(),
+        );
+        assert_eq!(unknown.distance_km(45.4862, 9.2046), None);
+    }
+
+    #[test]
+    fn board_diff_flags_platform_changes() {
+        let old: StationRealtime = serde_json::from_str(BOARD).unwrap();
+        let new: StationRealtime = serde_json::from_str(
+            &BOARD
+                .replace(r#""Binario": "7""#, r#""Binario": "9""#)
+                .replace("8158", "8160"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            old.diff(&new),
+            vec![
+                BoardDiff::Removed {
+                    train: "8158".to_owned()
+                },
+                BoardDiff::Added {
+                    train: "8160".to_owned()
+                },
+                BoardDiff::PlatformChanged {
+                    train: "9941".to_owned(),
+                    old: "7".to_owned(),
+                    new: "9".to_owned()
+                },
+            ]
+        );
+        assert!(old.diff(&old).is_empty());
+    }
+
+    #[test]
+    fn timeline_is_chronological() {
+        let board: StationRealtime = serde_json::from_str(
+            &BOARD.replace(r#""OraPassaggio": "10:05""#, r#""OraPassaggio": "10:45""#),
+        )
+        .unwrap();
+
+        let now = Rome
+            .with_ymd_and_hms(2024, 7, 15, 9, 0, 0)
+            .unwrap()
+            .to_utc();
+        let timeline = board.timeline(now);
+        assert_eq!(timeline.len(), 2);
+        assert_eq!(
+            timeline[0].time(),
+            Some(
+                Rome.with_ymd_and_hms(2024, 7, 15, 10, 15, 0)
+                    .unwrap()
+                    .to_utc()
+            )
+        );
+        assert_eq!(timeline[0].kind(), BoardKind::Departure);
+        assert_eq!(timeline[0].train().number(), "9941");
+        assert_eq!(timeline[1].kind(), BoardKind::Arrival);
+        assert!(timeline[0].time() < timeline[1].time());
+    }
+
+    #[test]
+    fn station_lookup() {
+        let stations = || {
+            vec![
+                Station::new("RMT".into(), "roma-termini".into(), "Roma Termini".into()),
+                Station::new(
+                    "RTB".into(),
+                    "roma-tiburtina".into(),
+                    "Roma Tiburtina".into(),
+                ),
+                Station::new(
+                    "MC_".into(),
+                    "milano-centrale".into(),
+                    "Milano Centrale".into(),
+                ),
+            ]
+        };
+
+        assert!(matches!(
+            StationLookup::search(stations(), "milano"),
+            StationLookup::Found(station) if station.code() == "MC_"
+        ));
+        assert!(matches!(
+            StationLookup::search(stations(), "roma termini"),
+            StationLookup::Found(station) if station.code() == "RMT"
+        ));
+        assert!(matches!(
+            StationLookup::search(stations(), "Roma"),
+            StationLookup::Ambiguous(candidates) if candidates.len() == 2
+        ));
+        assert!(matches!(
+            StationLookup::search(stations(), "Torino"),
+            StationLookup::NotFound
+        ));
+        assert_eq!(
+            StationLookup::search(stations(), "Roma")
+                .into_candidates()
+                .len(),
+            2
+        );
+        assert!(StationLookup::search(stations(), "Torino")
+            .into_candidates()
+            .is_empty());
+    }
 }
@@ -0,0 +1,225 @@
+use std::{fmt::Display, ops::Deref};
+
+use anyhow::Context;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::{Europe::Rome, Tz};
+use serde::Deserialize;
+
+/// Time as sent by italotreno: the `/Date(millis+0000)/` encoding of the journey search, italian local
+/// dateTimes or bare `HH:MM` times of the realtime services
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "binary-cache", derive(serde::Serialize))]
+#[serde(transparent)]
+pub struct ItaloDateTime(String);
+
+impl ItaloDateTime {
+    /// Raw value, empty when the server left the time blank
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// UTC dateTime, bare times are placed on the italian date of `now`, see [`crate::ItaloApi::now()`]
+    pub fn to_utc(&self, now: DateTime<Utc>) -> anyhow::Result<DateTime<Utc>> {
+        parse_realtime_time(&self.0, rome_date(now))
+    }
+
+    /// Milliseconds since the Unix epoch, see [`Self::to_utc()`]
+    pub fn epoch_millis(&self, now: DateTime<Utc>) -> anyhow::Result<i64> {
+        match self.is_encoded() {
+            true => extract_epoch_millis(&self.0),
+            false => Ok(self.to_utc(now)?.timestamp_millis()),
+        }
+    }
+
+    /// UTC dateTime of a value carrying its own date, bare times are an error
+    pub(crate) fn to_utc_dated(&self) -> anyhow::Result<DateTime<Utc>> {
+        parse_dated_time(&self.0)
+    }
+
+    /// Milliseconds since the Unix epoch of a value carrying its own date, see [`Self::to_utc_dated()`]
+    pub(crate) fn epoch_millis_dated(&self) -> anyhow::Result<i64> {
+        match self.is_encoded() {
+            true => extract_epoch_millis(&self.0),
+            false => Ok(self.to_utc_dated()?.timestamp_millis()),
+        }
+    }
+
+    fn is_encoded(&self) -> bool {
+        self.0.trim_start().starts_with("/Date(")
+    }
+}
+
+impl From<&str> for ItaloDateTime {
+    fn from(val: &str) -> Self {
+        Self(val.to_owned())
+    }
+}
+
+impl Deref for ItaloDateTime {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Display for ItaloDateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Extract the epoch millis from the `/Date(millis+0000)/` encoding used by italotreno, the offset is optional
+pub fn extract_epoch_millis(val: &str) -> anyhow::Result<i64> {
+    let millis = val.split_once('(').context("Failed to extract dateTime")?.1;
+    let end = millis
+        .char_indices()
+        .find(|(index, c)| !(c.is_ascii_digit() || (*index == 0 && *c == '-')))
+        .map_or(millis.len(), |(index, _)| index);
+
+    millis[..end]
+        .parse::<i64>()
+        .with_context(|| format!("Timestamp conversion failed for {val}"))
+}
+
+/// Extract the UTC dateTime from the `/Date(millis+0000)/` encoding used by italotreno
+pub fn extract_utc_time(val: &str) -> anyhow::Result<DateTime<Utc>> {
+    DateTime::from_timestamp(extract_epoch_millis(val)? / 1000, 0).context("invalid timestamp")
+}
+
+/// Date on the italian civil calendar at the instant `now`
+pub fn rome_date(now: DateTime<Utc>) -> NaiveDate {
+    now.with_timezone(&Rome).date_naive()
+}
+
+/// Parse a time carrying its own date: the `/Date(...)/` encoding or an italian local dateTime
+pub fn parse_dated_time(val: &str) -> anyhow::Result<DateTime<Utc>> {
+    let val = val.trim();
+    if val.starts_with("/Date(") {
+        return extract_utc_time(val);
+    }
+
+    let local = NaiveDateTime::parse_from_str(val, "%Y-%m-%dT%H:%M:%S")
+        .with_context(|| format!("Failed to parse dateTime {val}"))?;
+    rome_to_utc(local)
+}
+
+/// Parse a time reported by the realtime services.
+///
+/// Besides the values accepted by [`parse_dated_time()`], bare `HH:MM` times are accepted and placed on `date`.
+pub fn parse_realtime_time(val: &str, date: NaiveDate) -> anyhow::Result<DateTime<Utc>> {
+    parse_dated_time(val).or_else(|_| {
+        let val = val.trim();
+        let time = NaiveTime::parse_from_str(val, "%H:%M:%S")
+            .or_else(|_| NaiveTime::parse_from_str(val, "%H:%M"))
+            .with_context(|| format!("Failed to parse time {val}"))?;
+        rome_to_utc(date.and_time(time))
+    })
+}
+
+/// Minutes between two bare realtime times, wrapped around midnight
+pub fn delay_minutes(estimated: &str, actual: &str) -> Option<i64> {
+    // Any date works as both times are placed on the same one
+    let date = NaiveDate::default();
+    let estimated = parse_realtime_time(estimated, date).ok()?;
+    let actual = parse_realtime_time(actual, date).ok()?;
+    Some(wrap_day_minutes((actual - estimated).num_minutes()))
+}
+
+/// Bring a difference between two times of day placed on the same date within half a day, to cross midnight
+pub fn wrap_day_minutes(minutes: i64) -> i64 {
+    match minutes {
+        ..=-720 => minutes + 1440,
+        720.. => minutes - 1440,
+        _ => minutes,
+    }
+}
+
+/// UTC instants bounding the italian civil day `date`, the end is the start of the following day
+pub fn rome_day_bounds(date: NaiveDate) -> anyhow::Result<(DateTime<Utc>, DateTime<Utc>)> {
+    let next = date.succ_opt().context("Date out of range")?;
+    Ok((
+        rome_to_utc(date.and_time(NaiveTime::MIN))?,
+        rome_to_utc(next.and_time(NaiveTime::MIN))?,
+    ))
+}
+
+fn rome_to_utc(local: NaiveDateTime) -> anyhow::Result<DateTime<Utc>> {
+    Rome.from_local_datetime(&local)
+        .earliest()
+        .map(|elem: DateTime<Tz>| elem.with_timezone(&Utc))
+        .context("Non existent local time")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn realtime_time_formats() {
+        let date = NaiveDate::from_ymd_opt(2024, 7, 15).unwrap();
+        let expected = Utc.with_ymd_and_hms(2024, 7, 15, 8, 30, 0).unwrap();
+
+        assert_eq!(parse_realtime_time("10:30", date).unwrap(), expected);
+        assert_eq!(parse_realtime_time("10:30:00", date).unwrap(), expected);
+        assert_eq!(
+            parse_realtime_time("2024-07-15T10:30:00", date).unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_realtime_time("/Date(1721032200000+0000)/", date).unwrap(),
+            expected
+        );
+        assert!(parse_realtime_time("soon", date).is_err());
+    }
+
+    #[test]
+    fn malformed_timestamps() {
+        assert_eq!(
+            extract_epoch_millis("/Date(1721032200000+0000)/").unwrap(),
+            1721032200000
+        );
+        assert_eq!(
+            extract_epoch_millis("/Date(1721032200000)/").unwrap(),
+            1721032200000
+        );
+        assert_eq!(extract_epoch_millis("/Date(-1000+0000)/").unwrap(), -1000);
+
+        for val in [
+            "",
+            "/Date()/",
+            "/Date(+0000)/",
+            "/Date(abc+0000)/",
+            "/Date(99999999999999999999+0000)/",
+        ] {
+            assert!(extract_epoch_millis(val).is_err(), "{val}");
+        }
+        assert!(extract_utc_time("/Date(9223372036854775807+0000)/").is_err());
+    }
+
+    #[test]
+    fn italo_date_time() {
+        let encoded: ItaloDateTime =
+            serde_json::from_str(r#""/Date(1721032200000+0000)/""#).unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 7, 14, 23, 30, 0).unwrap();
+        assert_eq!(
+            encoded.to_utc(now).unwrap(),
+            Utc.with_ymd_and_hms(2024, 7, 15, 8, 30, 0).unwrap()
+        );
+        assert_eq!(encoded.epoch_millis(now).unwrap(), 1721032200000);
+        assert_eq!(
+            encoded.to_utc_dated().unwrap(),
+            encoded.to_utc(now).unwrap()
+        );
+
+        // Already July 15th in Rome
+        let bare = ItaloDateTime::from("10:30");
+        let expected = Utc.with_ymd_and_hms(2024, 7, 15, 8, 30, 0).unwrap();
+        assert_eq!(bare.to_utc(now).unwrap(), expected);
+        assert_eq!(bare.epoch_millis(now).unwrap(), expected.timestamp_millis());
+        assert!(bare.to_utc_dated().is_err());
+        assert_eq!(&*bare, "10:30");
+
+        assert!(ItaloDateTime::default().to_utc(now).is_err());
+    }
+}
@@ -1,8 +1,77 @@
+use std::fmt::Display;
+
+use anyhow::anyhow;
+use chrono::{DateTime, Duration, Utc};
 use getset::Getters;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    de::number_or_string,
+    time::{delay_minutes, ItaloDateTime},
+    ItaloError,
+};
+
+static TRAIN_NUMBER_PREFIXES: [&str; 4] = ["italo", "ita", "it", "ntv"];
+
+/// Italo train number normalized from user input
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TrainNumber(String);
+
+impl TrainNumber {
+    /// Extract the train number from pasted text like `"8158"`, `"ITA 8158"`, `"Italo 8158"` or `"italo-8158"`
+    pub fn parse(val: &str) -> anyhow::Result<Self> {
+        let val = val.trim();
+        let (prefix, number) =
+            val.split_at(val.find(|c: char| c.is_ascii_digit()).unwrap_or(val.len()));
+        let prefix = prefix
+            .trim_end_matches(|c: char| c.is_whitespace() || matches!(c, '-' | '.' | '#'))
+            .to_lowercase();
+        let number = number.trim();
+
+        if !(prefix.is_empty() || TRAIN_NUMBER_PREFIXES.contains(&prefix.as_str()))
+            || number.is_empty()
+            || !number.chars().all(|c| c.is_ascii_digit())
+        {
+            return Err(anyhow!("Unrecognized train number {val:?}"));
+        }
+        Ok(Self(number.to_owned()))
+    }
+
+    /// Numeric train ID as expected by the realtime service
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for TrainNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Parse the realtime service response, an HTML page is returned for unknown trains
+pub(crate) fn parse_train_realtime(
+    train_code: &str,
+    content_type: Option<&str>,
+    body: &str,
+) -> anyhow::Result<TrainRealtime> {
+    // The body decides when it looks like JSON or HTML, the content type only when it is neither
+    let body_start = body.trim_start();
+    let is_html = match body_start.chars().next() {
+        Some('<') => true,
+        Some('{' | '[') => false,
+        _ => content_type.is_some_and(|elem| elem.contains("html")),
+    };
+
+    match is_html {
+        true => Err(ItaloError::TrainNotFound(train_code.to_owned()).into()),
+        false => Ok(serde_json::from_str(body)?),
+    }
+}
 
 /// Realtime data for a train
 #[derive(Deserialize, Debug, Getters)]
+#[cfg_attr(feature = "binary-cache", derive(serde::Serialize))]
 #[serde(rename_all = "PascalCase")]
 #[get = "pub"]
 pub struct TrainRealtime {
@@ -10,8 +79,337 @@ pub struct TrainRealtime {
     train_schedule: TrainSchedule,
 }
 
+impl TrainRealtime {
+    /// Last station where the train has stopped, the departure station when it has not left yet
+    pub fn current_position(&self) -> &TrainStation {
+        let schedule = &self.train_schedule;
+        schedule
+            .stations_with_stop
+            .iter()
+            .max_by_key(|elem| elem.sequence)
+            .unwrap_or(&schedule.departure_station)
+    }
+
+    /// The train has reached its terminus: no station is left to serve and the last one has an actual arrival time.
+    ///
+    /// Useful to stop polling [`crate::ItaloApi::train_realtime()`] once the trip is over
+    pub fn is_completed(&self) -> bool {
+        let schedule = &self.train_schedule;
+        schedule.stations_with_transit.is_empty()
+            && schedule
+                .stations_with_stop
+                .iter()
+                .max_by_key(|elem| elem.sequence)
+                .is_some_and(|elem| !elem.actual_arrival_time.trim().is_empty())
+    }
+
+    /// Stop by stop view of the trip ordered by sequence, from the first station to the terminus.
+    ///
+    /// Passed stops carry the delay measured on the actual times, upcoming ones the current
+    /// train delay as forecast. The first station is passed once it has an actual departure time
+    /// or the train has stopped further on
+    pub fn progress(&self) -> Vec<StopProgress<'_>> {
+        let schedule = &self.train_schedule;
+        let passed = |station| StopProgress {
+            station,
+            state: StopState::Passed,
+            delay_minutes: station.time_delay(),
+        };
+        let upcoming = |station| StopProgress {
+            station,
+            state: StopState::Upcoming,
+            delay_minutes: Some(schedule.disruption.delay_amount.into()),
+        };
+
+        let origin = &schedule.departure_station;
+        let departed = !origin.actual_departure_time.trim().is_empty()
+            || !schedule.stations_with_stop.is_empty();
+        let origin = match departed {
+            true => passed(origin),
+            false => upcoming(origin),
+        };
+
+        let mut progress: Vec<_> = std::iter::once(origin)
+            .chain(schedule.stations_with_stop.iter().map(passed))
+            .chain(schedule.stations_with_transit.iter().map(upcoming))
+            .collect();
+        progress.sort_by_key(|elem| elem.station.sequence);
+        progress.dedup_by_key(|elem| elem.station.sequence);
+        progress
+    }
+
+    /// Difference (in minutes) between scheduled and actual times at every station of the trip, by Italo station ID.
+    ///
+    /// Follows [`Self::progress()`]: upcoming stations use their actual times when already
+    /// published, the current train delay otherwise. Stations with unparseable times are skipped
+    pub fn schedule_adherence(&self) -> Vec<(String, i64)> {
+        self.progress()
+            .iter()
+            .filter_map(|stop| {
+                let delay = stop.station.time_delay().or(stop.delay_minutes)?;
+                Some((stop.station.location_code.clone(), delay))
+            })
+            .collect()
+    }
+
+    /// Meaningful changes from an older snapshot of the same train, see [`crate::ItaloApi::train_events()`]
+    pub fn events_since(&self, previous: &TrainRealtime) -> Vec<TrainEvent> {
+        let (old, new) = (&previous.train_schedule, &self.train_schedule);
+        let mut events = Vec::new();
+
+        if old.disruption.delay_amount != new.disruption.delay_amount {
+            events.push(TrainEvent::DelayChanged {
+                old: old.disruption.delay_amount,
+                new: new.disruption.delay_amount,
+            });
+        }
+
+        for station in new.stations() {
+            let Some(before) = old
+                .stations()
+                .find(|elem| elem.location_code == station.location_code)
+            else {
+                continue;
+            };
+            let filled =
+                |before: &str, after: &str| before.trim().is_empty() && !after.trim().is_empty();
+
+            if before.any_platform() != station.any_platform() {
+                events.push(TrainEvent::PlatformChanged {
+                    station: station.location_code.clone(),
+                    old: before.any_platform().map(str::to_owned),
+                    new: station.any_platform().map(str::to_owned),
+                });
+            }
+            if filled(&before.actual_arrival_time, &station.actual_arrival_time) {
+                events.push(TrainEvent::Arrived {
+                    station: station.location_code.clone(),
+                });
+            }
+            if filled(
+                &before.actual_departure_time,
+                &station.actual_departure_time,
+            ) {
+                events.push(TrainEvent::Departed {
+                    station: station.location_code.clone(),
+                });
+            }
+        }
+        events
+    }
+
+    /// The train runs in place of another one, see [`TrainSchedule::replaces()`]
+    pub fn is_replacement(&self) -> bool {
+        self.train_schedule
+            .replaces
+            .as_deref()
+            .is_some_and(|train| !train.trim().is_empty())
+    }
+
+    /// The train has been replaced or renumbered, see [`TrainSchedule::replaced_by()`]
+    pub fn is_replaced(&self) -> bool {
+        self.train_schedule
+            .replaced_by
+            .as_deref()
+            .is_some_and(|train| !train.trim().is_empty())
+    }
+
+    /// First stop by sequence the train has not reached yet, suppressed stops are skipped.
+    ///
+    /// None once the train has arrived at its terminus
+    pub fn next_stop(&self) -> Option<&TrainStation> {
+        let schedule = &self.train_schedule;
+        schedule
+            .stations_with_stop
+            .iter()
+            .chain(&schedule.stations_with_transit)
+            .filter(|elem| elem.actual_arrival_time.trim().is_empty() && !elem.is_suppressed())
+            .min_by_key(|elem| elem.sequence)
+    }
+
+    /// Time left before the train reaches [`Self::next_stop()`], zero once overdue.
+    ///
+    /// Measured from `now`, see [`crate::ItaloApi::now()`], to its estimated arrival postponed by the current delay.
+    /// None when that time is unparseable
+    pub fn eta_to_next_stop(&self, now: DateTime<Utc>) -> Option<Duration> {
+        let arrival = self.next_stop()?.estimated_arrival_time.to_utc(now).ok()?
+            + Duration::minutes(self.train_schedule.disruption.delay_amount.into());
+        Some((arrival - now).max(Duration::zero()))
+    }
+
+    /// Compact view for notifications and widgets
+    pub fn summary(&self) -> TrainSummary {
+        let schedule = &self.train_schedule;
+        TrainSummary {
+            train_number: schedule.train_number.clone(),
+            origin: schedule.departure_station_name.clone(),
+            destination: schedule.arrival_station_name.clone(),
+            delay_minutes: schedule.disruption.delay_amount,
+            current_position: self.current_position().location_description.clone(),
+            next_stop: self
+                .next_stop()
+                .map(|elem| elem.location_description.clone()),
+        }
+    }
+
+    /// Scheduled arrival at the terminus postponed by the current delay.
+    ///
+    /// Bare times are placed on the italian date of `now`, see [`crate::ItaloApi::now()`], the arrival is
+    /// moved to the following day when it comes before the departure.
+    pub fn expected_arrival(&self, now: DateTime<Utc>) -> anyhow::Result<DateTime<Utc>> {
+        let schedule = &self.train_schedule;
+        let departure = schedule.departure_time.to_utc(now)?;
+        let mut arrival = schedule.arrival_time.to_utc(now)?;
+        if arrival < departure {
+            arrival += Duration::days(1);
+        }
+
+        Ok(arrival + Duration::minutes(schedule.disruption.delay_amount.into()))
+    }
+}
+
+impl TrainSchedule {
+    /// Every station of the trip, first station included, in response order
+    fn stations(&self) -> impl Iterator<Item = &TrainStation> {
+        std::iter::once(&self.departure_station)
+            .chain(&self.stations_with_stop)
+            .chain(&self.stations_with_transit)
+    }
+
+    /// First station of the trip
+    pub fn origin_station(&self) -> &TrainStation {
+        &self.departure_station
+    }
+
+    /// Last station of the trip, whether the train has already stopped there or not
+    pub fn terminus_station(&self) -> &TrainStation {
+        self.stations_with_stop
+            .iter()
+            .chain(&self.stations_with_transit)
+            .max_by_key(|elem| elem.sequence)
+            .unwrap_or(&self.departure_station)
+    }
+
+    /// Service category, read from the `TrainType` code of the realtime response when present, see [`ServiceType`]
+    pub fn service_type(&self) -> ServiceType {
+        ServiceType::from(self.service_code.as_deref().unwrap_or_default())
+    }
+
+    /// Scheduled departure time as milliseconds since the Unix epoch, bare times are on the italian date of `now`
+    pub fn departure_time_epoch_millis(&self, now: DateTime<Utc>) -> anyhow::Result<i64> {
+        self.departure_time.epoch_millis(now)
+    }
+
+    /// Scheduled arrival time as milliseconds since the Unix epoch, bare times are on the italian date of `now`
+    pub fn arrival_time_epoch_millis(&self, now: DateTime<Utc>) -> anyhow::Result<i64> {
+        self.arrival_time.epoch_millis(now)
+    }
+}
+
+impl TrainStation {
+    /// Estimated departure time as milliseconds since the Unix epoch, bare times are on the italian date of `now`
+    pub fn estimated_departure_time_epoch_millis(&self, now: DateTime<Utc>) -> anyhow::Result<i64> {
+        self.estimated_departure_time.epoch_millis(now)
+    }
+
+    /// Actual departure time as milliseconds since the Unix epoch, bare times are on the italian date of `now`
+    pub fn actual_departure_time_epoch_millis(&self, now: DateTime<Utc>) -> anyhow::Result<i64> {
+        self.actual_departure_time.epoch_millis(now)
+    }
+
+    /// Estimated arrival time as milliseconds since the Unix epoch, bare times are on the italian date of `now`
+    pub fn estimated_arrival_time_epoch_millis(&self, now: DateTime<Utc>) -> anyhow::Result<i64> {
+        self.estimated_arrival_time.epoch_millis(now)
+    }
+
+    /// Actual arrival time as milliseconds since the Unix epoch, bare times are on the italian date of `now`
+    pub fn actual_arrival_time_epoch_millis(&self, now: DateTime<Utc>) -> anyhow::Result<i64> {
+        self.actual_arrival_time.epoch_millis(now)
+    }
+}
+
+/// Change between two realtime snapshots of a train, see [`TrainRealtime::events_since()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrainEvent {
+    /// The train delay (in minutes) changed
+    DelayChanged { old: i32, new: i32 },
+
+    /// The platform of a station changed
+    PlatformChanged {
+        station: String,
+        old: Option<String>,
+        new: Option<String>,
+    },
+
+    /// The train left a station
+    Departed { station: String },
+
+    /// The train reached a station
+    Arrived { station: String },
+}
+
+/// Whether the train has already served a stop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopState {
+    /// The train has left or reached the station
+    Passed,
+
+    /// The train has still to reach the station
+    Upcoming,
+}
+
+/// Station of the trip placed on the train timeline, see [`TrainRealtime::progress()`]
+#[derive(Debug, Clone, Copy)]
+pub struct StopProgress<'a> {
+    station: &'a TrainStation,
+    state: StopState,
+    delay_minutes: Option<i64>,
+}
+
+impl<'a> StopProgress<'a> {
+    /// Station data with estimated and actual times
+    pub fn station(&self) -> &'a TrainStation {
+        self.station
+    }
+
+    /// Passed or upcoming
+    pub fn state(&self) -> StopState {
+        self.state
+    }
+
+    /// Delay (in minutes): measured for passed stops, forecast for upcoming ones.
+    /// `None` when the times can not be parsed
+    pub fn delay_minutes(&self) -> Option<i64> {
+        self.delay_minutes
+    }
+}
+
+/// Compact train status, see [`TrainRealtime::summary()`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Getters)]
+#[get = "pub"]
+pub struct TrainSummary {
+    /// Italo ID
+    train_number: String,
+
+    /// First trip station name
+    origin: String,
+
+    /// Terminus station name
+    destination: String,
+
+    /// Delay (in minutes)
+    delay_minutes: i32,
+
+    /// Name of the last station where the train has stopped
+    current_position: String,
+
+    /// Name of the next station, `None` once the train has reached its terminus
+    next_stop: Option<String>,
+}
+
 /// Train trip
 #[derive(Deserialize, Debug, Getters)]
+#[cfg_attr(feature = "binary-cache", derive(serde::Serialize))]
 #[get = "pub"]
 #[serde(rename_all = "PascalCase")]
 pub struct TrainSchedule {
@@ -23,7 +421,7 @@ pub struct TrainSchedule {
 
     /// Scheduled departure time
     #[serde(rename(deserialize = "DepartureDate"))]
-    departure_time: String,
+    departure_time: ItaloDateTime,
 
     ///First trip station name
     #[serde(rename(deserialize = "DepartureStationDescription"))]
@@ -31,7 +429,7 @@ pub struct TrainSchedule {
 
     /// Scheduled arrival time
     #[serde(rename(deserialize = "ArrivalDate"))]
-    arrival_time: String,
+    arrival_time: ItaloDateTime,
 
     /// Terminus station
     #[serde(rename(deserialize = "ArrivalStationDescription"))]
@@ -46,20 +444,77 @@ pub struct TrainSchedule {
     departure_station: TrainStation,
 
     /// Stations where the train has already stopped
-    #[serde(rename(deserialize = "StazioniFerme"))]
+    #[serde(
+        rename(deserialize = "StazioniFerme"),
+        deserialize_with = "passed_stations"
+    )]
     stations_with_stop: Vec<TrainStation>,
 
     /// Stations where it will stop
     #[serde(rename(deserialize = "StazioniNonFerme"))]
     stations_with_transit: Vec<TrainStation>,
+
+    #[serde(default, rename(deserialize = "TrainType"))]
+    #[getset(skip)]
+    service_code: Option<String>,
+
+    /// Number of the train this one runs in place of, read from a `Replaces` (or `Sostituisce`) key.
+    ///
+    /// Best-effort guess: no captured realtime response carries these keys yet, so expect `None`
+    /// until they are confirmed
+    #[serde(default, rename(deserialize = "Replaces"), alias = "Sostituisce")]
+    replaces: Option<String>,
+
+    /// Number of the train running in place of this one, read from a `ReplacedBy` (or `SostituitoDa`) key.
+    ///
+    /// Best-effort guess like [`Self::replaces()`]
+    #[serde(default, rename(deserialize = "ReplacedBy"), alias = "SostituitoDa")]
+    replaced_by: Option<String>,
+}
+
+/// Rolling stock / service category of a train.
+///
+/// Mapped from the `TrainType` key of the realtime and search responses by looking for the italo
+/// marketing names (`AGV`/`575`, `EVO`, `Italo Più`) in the code. Best-effort: no captured response
+/// carries that key yet, so expect [`ServiceType::Unknown`] until it is confirmed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServiceType {
+    /// Alstom AGV 575
+    Agv575,
+
+    /// Italo EVO
+    Evo,
+
+    /// Italo Più service
+    ItaloPiu,
+
+    /// Category code not recognized, empty when not provided
+    Unknown(String),
+}
+
+impl From<&str> for ServiceType {
+    fn from(val: &str) -> Self {
+        let code = val.trim().to_uppercase();
+        if code.contains("AGV") || code.contains("575") {
+            ServiceType::Agv575
+        } else if code.contains("EVO") {
+            ServiceType::Evo
+        } else if code.contains("PIU") || code.contains("PIÙ") {
+            ServiceType::ItaloPiu
+        } else {
+            ServiceType::Unknown(val.to_owned())
+        }
+    }
 }
 
 /// Disruption data
 #[derive(Deserialize, Debug, Getters)]
+#[cfg_attr(feature = "binary-cache", derive(serde::Serialize))]
 #[serde(rename_all = "PascalCase")]
 #[get = "pub"]
 pub struct Disruption {
     /// Delay (in minutes)
+    #[serde(deserialize_with = "number_or_string")]
     delay_amount: i32,
 
     /// Unknown
@@ -69,11 +524,13 @@ pub struct Disruption {
     warning: bool,
 
     /// Unknown
+    #[serde(deserialize_with = "number_or_string")]
     running_state: u16,
 }
 
 /// Station data enriched with train information
 #[derive(Deserialize, Debug, Getters)]
+#[cfg_attr(feature = "binary-cache", derive(serde::Serialize))]
 #[serde(rename_all = "PascalCase")]
 #[get = "pub"]
 pub struct TrainStation {
@@ -87,22 +544,466 @@ pub struct TrainStation {
     rfi_location_code: String,
 
     /// Estimated time by which the train will leave the station
-    estimated_departure_time: String,
+    estimated_departure_time: ItaloDateTime,
 
     /// Real time by which the train will leave the station
-    actual_departure_time: String,
+    actual_departure_time: ItaloDateTime,
 
     /// Estimated time by which the train will arrive to the station
-    estimated_arrival_time: String,
+    estimated_arrival_time: ItaloDateTime,
 
     /// Real time by which the train will arrive to the station
-    actual_arrival_time: String,
+    actual_arrival_time: ItaloDateTime,
 
-    /// Platform
-    #[serde(rename(deserialize = "ActualArrivalPlatform"))]
+    /// Platform, as reported on arrival. See [`Self::any_platform()`] for a fallback on the other keys
+    #[serde(default, rename(deserialize = "ActualArrivalPlatform"))]
     platform: Option<String>,
 
+    /// Departure platform
+    #[serde(default, rename(deserialize = "ActualDeparturePlatform"))]
+    departure_platform: Option<String>,
+
+    /// Platform as named by the italian version of the service
+    #[serde(default, rename(deserialize = "Binario"))]
+    binario: Option<String>,
+
     /// Station index in the trip plan
-    #[serde(rename(deserialize = "StationNumber"))]
-    sequence: u8,
+    #[serde(
+        rename(deserialize = "StationNumber"),
+        deserialize_with = "number_or_string"
+    )]
+    sequence: u16,
+
+    /// Speculative `Soppressa`/`Suppressed` flag, not seen in a captured response yet
+    #[serde(default, rename(deserialize = "Soppressa"), alias = "Suppressed")]
+    #[getset(skip)]
+    suppressed: Option<bool>,
+
+    /// Listed among the stations the train has already passed
+    #[serde(skip)]
+    #[getset(skip)]
+    passed: bool,
+}
+
+fn passed_stations<'de, D>(deserializer: D) -> Result<Vec<TrainStation>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut stations = Vec::<TrainStation>::deserialize(deserializer)?;
+    stations.iter_mut().for_each(|elem| elem.passed = true);
+    Ok(stations)
+}
+
+impl TrainStation {
+    /// Platform of the stop: the first non-empty among the arrival, departure and `Binario` ones
+    pub fn any_platform(&self) -> Option<&str> {
+        [&self.platform, &self.departure_platform, &self.binario]
+            .into_iter()
+            .flatten()
+            .map(|platform| platform.trim())
+            .find(|platform| !platform.is_empty())
+    }
+
+    /// The train skips this scheduled stop.
+    ///
+    /// A stop is suppressed when the train has already passed it without actual times, despite an estimated one,
+    /// so upcoming suppressed stops are only detected once the train is past them.
+    ///
+    /// Should the response carry a `Soppressa` (or `Suppressed`) flag it takes precedence, but that key is
+    /// a guess not confirmed by a real response
+    pub fn is_suppressed(&self) -> bool {
+        self.suppressed.unwrap_or_else(|| {
+            let blank = |time: &ItaloDateTime| time.trim().is_empty();
+            self.passed
+                && !(blank(&self.estimated_arrival_time) && blank(&self.estimated_departure_time))
+                && blank(&self.actual_arrival_time)
+                && blank(&self.actual_departure_time)
+        })
+    }
+
+    /// Delay (in minutes) between estimated and actual times, the arrival one when available
+    fn time_delay(&self) -> Option<i64> {
+        delay_minutes(&self.estimated_arrival_time, &self.actual_arrival_time)
+            .or_else(|| delay_minutes(&self.estimated_departure_time, &self.actual_departure_time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn train_number_paste_formats() {
+        for val in [
+            "8158",
+            " 8158 ",
+            "ITA 8158",
+            "Italo 8158",
+            "italo-8158",
+            "NTV8158",
+            "It. 8158",
+        ] {
+            assert_eq!(TrainNumber::parse(val).unwrap().as_str(), "8158", "{val}");
+        }
+
+        for val in ["", "Italo", "Frecciarossa 9500", "8158 bis", "81 58"] {
+            assert!(TrainNumber::parse(val).is_err(), "{val}");
+        }
+    }
+
+    #[test]
+    fn html_body_is_train_not_found() {
+        let res = parse_train_realtime(
+            "0000",
+            Some("text/html; charset=utf-8"),
+            "<!DOCTYPE html><html><body>Errore</body></html>",
+        );
+        assert_eq!(
+            res.unwrap_err().downcast_ref::<ItaloError>(),
+            Some(&ItaloError::TrainNotFound("0000".to_owned()))
+        );
+
+        let res = parse_train_realtime("0000", Some("text/html"), "Errore");
+        assert!(res
+            .unwrap_err()
+            .downcast_ref::<ItaloError>()
+            .is_some_and(|err| matches!(err, ItaloError::TrainNotFound(_))));
+
+        let res = parse_train_realtime(
+            "8158",
+            Some("text/plain"),
+            include_str!("../../tests/fixtures/train_realtime_8158.json"),
+        );
+        assert!(res.is_ok());
+
+        let res = parse_train_realtime("0000", None, "  <html></html>");
+        assert!(res
+            .unwrap_err()
+            .downcast_ref::<ItaloError>()
+            .is_some_and(|err| matches!(err, ItaloError::TrainNotFound(_))));
+    }
+
+    #[test]
+    fn train_station_platform_keys() {
+        for fixture in [
+            include_str!("../../tests/fixtures/train_station_arrival_platform.json"),
+            include_str!("../../tests/fixtures/train_station_departure_platform.json"),
+            include_str!("../../tests/fixtures/train_station_binario.json"),
+            include_str!("../../tests/fixtures/train_station_both_platforms.json"),
+        ] {
+            let station: TrainStation = serde_json::from_str(fixture).unwrap();
+            assert_eq!(station.any_platform(), Some("14"), "{fixture}");
+        }
+
+        let mut raw: serde_json::Value = serde_json::from_str(include_str!(
+            "../../tests/fixtures/train_station_both_platforms.json"
+        ))
+        .unwrap();
+        raw["ActualArrivalPlatform"] = "13".into();
+        let station: TrainStation = serde_json::from_value(raw.clone()).unwrap();
+        assert_eq!(station.any_platform(), Some("13"));
+        assert_eq!(station.platform().as_deref(), Some("13"));
+        assert_eq!(station.departure_platform().as_deref(), Some("14"));
+
+        raw.as_object_mut().unwrap().remove("ActualArrivalPlatform");
+        raw.as_object_mut()
+            .unwrap()
+            .remove("ActualDeparturePlatform");
+        let station: TrainStation = serde_json::from_value(raw).unwrap();
+        assert_eq!(station.any_platform(), None);
+        assert_eq!(station.platform(), &None);
+    }
+
+    #[test]
+    fn service_type_codes() {
+        for (code, service) in [
+            ("AGV 575", ServiceType::Agv575),
+            ("ETR575", ServiceType::Agv575),
+            ("evo", ServiceType::Evo),
+            ("Italo Più", ServiceType::ItaloPiu),
+            ("ITALO PIU", ServiceType::ItaloPiu),
+            ("", ServiceType::Unknown(String::new())),
+            ("ETR1000", ServiceType::Unknown("ETR1000".to_owned())),
+        ] {
+            assert_eq!(ServiceType::from(code), service, "{code}");
+        }
+    }
+
+    #[test]
+    fn train_summary() {
+        let train: TrainRealtime = serde_json::from_str(include_str!(
+            "../../tests/fixtures/train_realtime_8158.json"
+        ))
+        .unwrap();
+        let summary = train.summary();
+
+        assert_eq!(summary.train_number(), "8158");
+        assert_eq!(summary.origin(), "Napoli Centrale");
+        assert_eq!(summary.destination(), "Milano Centrale");
+        assert_eq!(summary.delay_minutes(), &3);
+        assert_eq!(summary.current_position(), "Firenze S.M. Novella");
+        assert_eq!(summary.next_stop().as_deref(), Some("Bologna Centrale"));
+    }
+
+    #[test]
+    fn replacement_trains() {
+        let mut raw: serde_json::Value = serde_json::from_str(include_str!(
+            "../../tests/fixtures/train_realtime_8158.json"
+        ))
+        .unwrap();
+        let train: TrainRealtime = serde_json::from_value(raw.clone()).unwrap();
+        assert!(!train.is_replacement());
+        assert!(!train.is_replaced());
+
+        raw["TrainSchedule"]["Sostituisce"] = "8160".into();
+        let train: TrainRealtime = serde_json::from_value(raw).unwrap();
+        assert!(train.is_replacement());
+        assert_eq!(train.train_schedule().replaces().as_deref(), Some("8160"));
+    }
+
+    #[test]
+    fn train_progress() {
+        let train: TrainRealtime = serde_json::from_str(include_str!(
+            "../../tests/fixtures/train_realtime_8158.json"
+        ))
+        .unwrap();
+        let progress = train.progress();
+
+        let codes: Vec<_> = progress
+            .iter()
+            .map(|elem| elem.station().location_code().as_str())
+            .collect();
+        assert_eq!(codes, vec!["NAC", "RMT", "SMN", "BO_", "MC_"]);
+
+        let states: Vec<_> = progress.iter().map(StopProgress::state).collect();
+        assert_eq!(
+            states,
+            vec![
+                StopState::Passed,
+                StopState::Passed,
+                StopState::Passed,
+                StopState::Upcoming,
+                StopState::Upcoming
+            ]
+        );
+
+        let delays: Vec<_> = progress.iter().map(StopProgress::delay_minutes).collect();
+        assert_eq!(delays, vec![Some(0), Some(1), Some(3), Some(3), Some(3)]);
+
+        let adherence = train.schedule_adherence();
+        assert_eq!(adherence.len(), 5);
+        assert_eq!(adherence[1], ("RMT".to_owned(), 1));
+        assert_eq!(adherence[4], ("MC_".to_owned(), 3));
+    }
+
+    #[test]
+    fn next_stop_eta() {
+        let train: TrainRealtime = serde_json::from_str(include_str!(
+            "../../tests/fixtures/train_realtime_8158.json"
+        ))
+        .unwrap();
+        let next = train.next_stop().unwrap();
+        assert_eq!(next.location_code(), "BO_");
+
+        let now = DateTime::from_timestamp(1721026800, 0).unwrap();
+        let scheduled = next.estimated_arrival_time().to_utc(now).unwrap();
+        let eta = train.eta_to_next_stop(scheduled - Duration::minutes(10));
+        assert_eq!(eta, Some(Duration::minutes(13)));
+        let eta = train.eta_to_next_stop(scheduled + Duration::hours(1));
+        assert_eq!(eta, Some(Duration::zero()));
+    }
+
+    #[test]
+    fn progress_before_departure() {
+        let mut raw: serde_json::Value = serde_json::from_str(include_str!(
+            "../../tests/fixtures/train_realtime_8158.json"
+        ))
+        .unwrap();
+        let schedule = &mut raw["TrainSchedule"];
+        schedule["StazionePartenza"]["ActualDepartureTime"] = "".into();
+        let passed = schedule["StazioniFerme"].take();
+        schedule["StazioniFerme"] = serde_json::json!([]);
+        let transit = schedule["StazioniNonFerme"].as_array_mut().unwrap();
+        for (pos, mut station) in passed.as_array().unwrap().iter().cloned().enumerate() {
+            station["ActualArrivalTime"] = "".into();
+            station["ActualDepartureTime"] = "".into();
+            transit.insert(pos, station);
+        }
+        let train: TrainRealtime = serde_json::from_value(raw).unwrap();
+
+        let progress = train.progress();
+        assert_eq!(progress[0].station().location_code(), "NAC");
+        assert!(progress
+            .iter()
+            .all(|stop| stop.state() == StopState::Upcoming));
+        assert_eq!(progress[0].delay_minutes(), Some(3));
+    }
+
+    #[test]
+    fn suppressed_stops() {
+        let mut raw: serde_json::Value = serde_json::from_str(include_str!(
+            "../../tests/fixtures/train_realtime_8158.json"
+        ))
+        .unwrap();
+        let train: TrainRealtime = serde_json::from_value(raw.clone()).unwrap();
+        assert!(!train
+            .progress()
+            .iter()
+            .any(|stop| stop.station().is_suppressed()));
+
+        let schedule = &mut raw["TrainSchedule"];
+        let roma = &mut schedule["StazioniFerme"][0];
+        roma["ActualArrivalTime"] = "".into();
+        roma["ActualDepartureTime"] = "".into();
+        schedule["StazioniNonFerme"][1]["Soppressa"] = true.into();
+        let train: TrainRealtime = serde_json::from_value(raw).unwrap();
+
+        let suppressed: Vec<_> = train
+            .progress()
+            .iter()
+            .filter(|stop| stop.station().is_suppressed())
+            .map(|stop| stop.station().location_code().as_str())
+            .collect();
+        assert_eq!(suppressed, ["RMT", "MC_"]);
+    }
+
+    #[test]
+    fn midnight_delay() {
+        assert_eq!(delay_minutes("23:58", "00:03"), Some(5));
+        assert_eq!(delay_minutes("00:02", "23:59"), Some(-3));
+        assert_eq!(delay_minutes("", "10:00"), None);
+    }
+
+    #[test]
+    fn sequence_as_number_or_string() {
+        let train: TrainRealtime = serde_json::from_str(include_str!(
+            "../../tests/fixtures/train_realtime_string_sequence.json"
+        ))
+        .unwrap();
+        let schedule = train.train_schedule();
+
+        assert_eq!(schedule.origin_station().sequence(), &0);
+        assert_eq!(schedule.terminus_station().sequence(), &4);
+        assert_eq!(train.current_position().location_code(), "SMN");
+
+        let long_route: TrainStation = serde_json::from_str(
+            r#"{"LocationCode": "MC_", "LocationDescription": "Milano Centrale", "RfiLocationCode": "S01700", "EstimatedDepartureTime": "", "ActualDepartureTime": "", "EstimatedArrivalTime": "", "ActualArrivalTime": "", "StationNumber": "300"}"#,
+        )
+        .unwrap();
+        assert_eq!(long_route.sequence(), &300);
+    }
+
+    #[test]
+    fn events_between_snapshots() {
+        let mut raw: serde_json::Value = serde_json::from_str(include_str!(
+            "../../tests/fixtures/train_realtime_8158.json"
+        ))
+        .unwrap();
+        let before: TrainRealtime = serde_json::from_value(raw.clone()).unwrap();
+        assert!(before.events_since(&before).is_empty());
+
+        let schedule = &mut raw["TrainSchedule"];
+        schedule["Distruption"]["DelayAmount"] = 5.into();
+        let bologna = &mut schedule["StazioniNonFerme"][0];
+        bologna["ActualArrivalTime"] = "09:44".into();
+        bologna["ActualArrivalPlatform"] = "16".into();
+        let after: TrainRealtime = serde_json::from_value(raw).unwrap();
+
+        assert_eq!(
+            after.events_since(&before),
+            vec![
+                TrainEvent::DelayChanged { old: 3, new: 5 },
+                TrainEvent::PlatformChanged {
+                    station: "BO_".to_owned(),
+                    old: None,
+                    new: Some("16".to_owned())
+                },
+                TrainEvent::Arrived {
+                    station: "BO_".to_owned()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn trip_endpoints() {
+        let train: TrainRealtime = serde_json::from_str(include_str!(
+            "../../tests/fixtures/train_realtime_8158.json"
+        ))
+        .unwrap();
+        let schedule = train.train_schedule();
+
+        assert_eq!(
+            schedule.origin_station().location_description(),
+            "Napoli Centrale"
+        );
+        assert_eq!(schedule.terminus_station().location_code(), "MC_");
+    }
+
+    #[test]
+    fn completed_trip() {
+        let mut raw: serde_json::Value = serde_json::from_str(include_str!(
+            "../../tests/fixtures/train_realtime_8158.json"
+        ))
+        .unwrap();
+        let train: TrainRealtime = serde_json::from_value(raw.clone()).unwrap();
+        assert!(!train.is_completed());
+
+        let schedule = &mut raw["TrainSchedule"];
+        let mut remaining = schedule["StazioniNonFerme"].take();
+        remaining
+            .as_array_mut()
+            .unwrap()
+            .iter_mut()
+            .for_each(|station| {
+                station["ActualArrivalTime"] = station["EstimatedArrivalTime"].clone()
+            });
+        schedule["StazioniFerme"]
+            .as_array_mut()
+            .unwrap()
+            .extend(remaining.as_array().unwrap().iter().cloned());
+        schedule["StazioniNonFerme"] = serde_json::json!([]);
+
+        let train: TrainRealtime = serde_json::from_value(raw).unwrap();
+        assert!(train.is_completed());
+        assert_eq!(train.summary().current_position(), "Milano Centrale");
+    }
+
+    #[test]
+    fn disruption_numbers_as_number_or_string() {
+        let numbers: Disruption = serde_json::from_str(
+            r#"{"DelayAmount": 5, "LocationCode": "RMT", "Warning": false, "RunningState": 1}"#,
+        )
+        .unwrap();
+        assert_eq!(numbers.delay_amount(), &5);
+        assert_eq!(numbers.running_state(), &1);
+
+        let strings: Disruption = serde_json::from_str(
+            r#"{"DelayAmount": "-2", "LocationCode": "RMT", "Warning": false, "RunningState": " 1"}"#,
+        )
+        .unwrap();
+        assert_eq!(strings.delay_amount(), &-2);
+        assert_eq!(strings.running_state(), &1);
+
+        assert!(serde_json::from_str::<Disruption>(
+            r#"{"DelayAmount": "five", "LocationCode": "RMT", "Warning": false, "RunningState": 1}"#,
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn string_encoded_disruption_fixture() {
+        let body = crate::fixture::load(
+            "train_realtime_string_disruption.json",
+            &crate::EndpointConfig::default().train_realtime("8158"),
+        )
+        .await;
+        let train: TrainRealtime = serde_json::from_str(&body).unwrap();
+
+        if !crate::fixture::capturing() {
+            let disruption = train.train_schedule().disruption();
+            assert_eq!(disruption.delay_amount(), &12);
+            assert_eq!(disruption.running_state(), &2);
+        }
+    }
 }